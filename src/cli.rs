@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use clap::{ArgAction, Parser};
+use clap::{ArgAction, Parser, ValueEnum};
 
 /// Scan the given directory for Git repositories and display their status.
 /// A Repository turns red if it has unpushed changes.
@@ -28,7 +28,216 @@ pub struct Args {
     /// Note: This may take a while for large repositories.
     #[arg(short, long, action = ArgAction::SetTrue)]
     pub fetch: bool,
+    /// Remotes to fetch with --fetch (comma-separated)
+    #[arg(long, value_delimiter = ',', default_value = "origin")]
+    pub fetch_remotes: Vec<String>,
+    /// Fetch all configured remotes with --fetch instead of --fetch-remotes
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub all_remotes: bool,
+    /// Maximum number of repositories fetched at the same time with --fetch
+    #[arg(short, long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub jobs: Option<usize>,
+    /// Abandon fetching the remotes of a repository after the given number of seconds
+    #[arg(long, value_name = "SECONDS")]
+    pub fetch_timeout: Option<u64>,
     /// Print a legend explaining the color codes and statuses used in the output
     #[arg(short, long, action = ArgAction::SetTrue)]
     pub legend: bool,
+    /// Output format of the scan results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+    /// Columns to show, in the given order (comma-separated).
+    /// Defaults to directory, branch, upstream, ahead, behind, untracked, stash and status,
+    /// plus remote if --remote is set.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Vec<Column>,
+    /// Columns to hide from the selected columns (comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub hide_columns: Vec<Column>,
+    /// Sort the repositories by the given key
+    #[arg(long, value_enum, default_value_t = SortKey::Name)]
+    pub sort: SortKey,
+    /// Reverse the sort order
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub reverse: bool,
+    /// Only show repositories in any of the given states (comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub only: Vec<StateFilter>,
+    /// Hide repositories in any of the given states (comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub exclude: Vec<StateFilter>,
+    /// Only show repositories whose last commit is older than the given duration,
+    /// e.g. "90days", "6months" or "1y"
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub stale: Option<std::time::Duration>,
+    /// Exit with a non-zero code if any repository needs attention.
+    /// The code is the sum of: 2 = dirty, 4 = unpushed commits, 8 = behind upstream,
+    /// 16 = failed to process. 1 is reserved for fatal errors and invalid arguments.
+    /// Only the repositories kept by --only, --exclude and --stale are checked
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub check: bool,
+    /// List the unpushed local branches and the linked worktrees of every repository below the table
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub details: bool,
+    /// Do not show the progress on stderr while scanning.
+    /// It is also hidden if stderr is not a terminal or a machine-readable format is selected.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub no_progress: bool,
+}
+
+impl Args {
+    /// Returns the columns to display, honouring `--columns`, `--hide-columns` and `--remote`.
+    pub fn selected_columns(&self) -> Vec<Column> {
+        let mut columns = if self.columns.is_empty() {
+            let mut columns = Column::DEFAULT.to_vec();
+            if self.remote {
+                columns.push(Column::Remote);
+            }
+            columns
+        } else {
+            self.columns.clone()
+        };
+        columns.retain(|c| !self.hide_columns.contains(c));
+        columns
+    }
+}
+
+/// Keys the repositories can be sorted by.
+/// Ties are always broken by the case-insensitive directory name.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Case-insensitive directory name
+    #[default]
+    Name,
+    /// Full path
+    Path,
+    /// Current branch
+    Branch,
+    /// Commits ahead of upstream
+    Ahead,
+    /// Commits behind upstream
+    Behind,
+    /// Commits behind the remote's default branch
+    BehindDefault,
+    /// Number of changed files
+    Changed,
+    /// Total number of commits
+    Commits,
+    /// Date of the last commit
+    LastCommit,
+    /// Clean or dirty
+    Status,
+}
+
+/// Repository states used by `--only` and `--exclude`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateFilter {
+    /// No changes in the working directory
+    Clean,
+    /// Changes in the working directory
+    Dirty,
+    /// Commits not pushed to the upstream
+    Unpushed,
+    /// Commits on the upstream that are not pulled
+    Behind,
+    /// The current branch has no upstream, or it is gone
+    NoUpstream,
+    /// The upstream of the current branch was deleted
+    UpstreamGone,
+    /// HEAD is detached
+    Detached,
+    /// A merge, rebase, cherry-pick, revert, bisect or `git am` is in progress
+    InProgress,
+}
+
+/// Columns of the repository table and its exports.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    /// Directory name of the repository
+    #[value(alias = "name")]
+    Directory,
+    /// Full path of the repository
+    Path,
+    /// Current branch
+    Branch,
+    /// Upstream of the current branch
+    Upstream,
+    /// Commits ahead of upstream
+    Ahead,
+    /// Commits behind upstream
+    Behind,
+    /// Commits ahead of and behind the remote's default branch, e.g. "↑2 ↓15 origin/main"
+    DefaultBranch,
+    /// Total number of commits in the current branch, capped at 10000.
+    /// Counting walks the whole history, so it is only done if this column is selected
+    Commits,
+    /// Number of untracked files
+    Untracked,
+    /// Number of changed files, including untracked ones
+    Changed,
+    /// Compact file counts per kind of change, e.g. "+3 ~2 -1 !1 ?4"
+    Files,
+    /// Number of stashed changes
+    Stash,
+    /// Number of local branches with unpushed commits
+    UnpushedBranches,
+    /// Time since the last commit, e.g. "3 days ago"
+    Age,
+    /// Author of the last commit
+    Author,
+    /// Summary line of the last commit
+    LastCommit,
+    /// Clean or dirty
+    Status,
+    /// Remote URL of origin
+    Remote,
+    /// All remotes with their URLs
+    Remotes,
+    /// Linked worktrees with their branch and state
+    Worktrees,
+}
+
+impl Column {
+    /// Columns shown when `--columns` is not given.
+    pub const DEFAULT: [Self; 8] = [
+        Self::Directory,
+        Self::Branch,
+        Self::Upstream,
+        Self::Ahead,
+        Self::Behind,
+        Self::Untracked,
+        Self::Stash,
+        Self::Status,
+    ];
+}
+
+/// Output formats supported by the scan.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable table
+    #[default]
+    Table,
+    /// Versioned JSON document containing all repositories and failures
+    Json,
+    /// One JSON object per line, streamed as soon as each repository is scanned,
+    /// followed by a summary record
+    Ndjson,
+    /// Comma-separated values with the same columns as the table
+    Csv,
+    /// Tab-separated values with the same columns as the table
+    Tsv,
+    /// Markdown table with emoji markers instead of colors
+    Markdown,
+    /// Standalone HTML page with CSS classes instead of colors
+    Html,
+}
+
+impl OutputFormat {
+    /// Returns true if the format is meant to be consumed by other programs.
+    pub const fn is_machine_readable(self) -> bool {
+        match self {
+            Self::Json | Self::Ndjson | Self::Csv | Self::Tsv => true,
+            Self::Table | Self::Markdown | Self::Html => false,
+        }
+    }
 }
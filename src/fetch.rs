@@ -0,0 +1,261 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use git2::{
+    AutotagOption, CertificateCheckStatus, Cred, CredentialType, FetchOptions, ProxyOptions,
+    RemoteCallbacks,
+};
+
+use crate::cli::Args;
+
+/// SSH key files tried, in order, when the SSH agent cannot authenticate.
+const SSH_KEY_FILES: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Settings of the fetch run before a repository is scanned.
+#[derive(Clone, Debug, Default)]
+pub struct FetchConfig {
+    /// Names of the remotes to fetch; all configured remotes if empty.
+    pub remotes: Vec<String>,
+    /// Time after which fetching the remotes of a repository is abandoned.
+    pub timeout: Option<Duration>,
+}
+
+impl FetchConfig {
+    /// Creates the fetch settings from the CLI arguments, or `None` if `--fetch` is not set.
+    pub fn from_args(args: &Args) -> Option<Self> {
+        args.fetch.then(|| Self {
+            remotes: if args.all_remotes {
+                Vec::new()
+            } else {
+                args.fetch_remotes.clone()
+            },
+            timeout: args.fetch_timeout.map(Duration::from_secs),
+        })
+    }
+}
+
+/// Error returned when fetching the remotes of a repository took longer than the configured timeout.
+#[derive(Debug)]
+pub struct FetchTimedOut {
+    /// The exceeded timeout.
+    pub timeout: Duration,
+}
+
+impl fmt::Display for FetchTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fetch timed out after {}s", self.timeout.as_secs_f32())
+    }
+}
+
+impl std::error::Error for FetchTimedOut {}
+
+/// Fetches the remotes selected by `config`.
+///
+/// Every remote is fetched even if an earlier one fails. With a timeout, the remotes are
+/// fetched on a separate thread and the timeout applies to all of them together.
+/// An abandoned fetch is left to that thread, which stops it at the next libgit2 callback,
+/// so the caller is free to fetch the next repository right away.
+///
+/// # Errors
+/// Returns a [`FetchTimedOut`] error on timeout,
+/// otherwise an error listing every remote that could not be fetched.
+pub fn fetch(repo: &git2::Repository, config: &FetchConfig) -> anyhow::Result<()> {
+    let remotes = if config.remotes.is_empty() {
+        repo.remotes()?
+            .iter()
+            .flatten()
+            .map(str::to_owned)
+            .collect()
+    } else {
+        config.remotes.clone()
+    };
+    let Some(timeout) = config.timeout else {
+        return fetch_remotes(repo, &remotes, &AtomicBool::new(false));
+    };
+    let git_dir = repo.path().to_path_buf();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let thread_cancelled = Arc::clone(&cancelled);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let result = git2::Repository::open(&git_dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|repo| fetch_remotes(&repo, &remotes, &thread_cancelled));
+        // The receiver is gone if the fetch timed out
+        sender.send(result).ok();
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            cancelled.store(true, Ordering::Relaxed);
+            Err(FetchTimedOut { timeout }.into())
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+            anyhow::bail!("Fetch stopped unexpectedly")
+        }
+    }
+}
+
+/// Fetches the given remotes one after another, stopping early once `cancelled` is set.
+///
+/// # Errors
+/// Returns an error listing every remote that could not be fetched.
+fn fetch_remotes(
+    repo: &git2::Repository,
+    remotes: &[String],
+    cancelled: &AtomicBool,
+) -> anyhow::Result<()> {
+    let errors = remotes
+        .iter()
+        .take_while(|_| !cancelled.load(Ordering::Relaxed))
+        .filter_map(|remote| fetch_remote(repo, remote, cancelled).err())
+        .map(|e| format!("{e:#}"))
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        anyhow::bail!(errors.join("; "));
+    }
+    Ok(())
+}
+
+/// Fetches the given remote with git2 to update the remote-tracking branches.
+///
+/// Authentication is attempted with the SSH agent, the default SSH key files and
+/// the configured git credential helpers, in that order.
+///
+/// The fetch is aborted as soon as `cancelled` is set and libgit2 calls back, i.e. when it
+/// asks for credentials, checks the certificate or reports progress.
+/// A connection that hangs before any of these cannot be interrupted.
+///
+/// # Errors
+/// Returns an error if the remote does not exist, the fetch fails or it was cancelled.
+pub fn fetch_remote(
+    repo: &git2::Repository,
+    remote_name: &str,
+    cancelled: &AtomicBool,
+) -> anyhow::Result<()> {
+    let mut remote = repo
+        .find_remote(remote_name)
+        .with_context(|| format!("Failed to find remote '{remote_name}'"))?;
+    let config = repo.config()?;
+
+    let mut tried = TriedCredentials::default();
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(git2::Error::from_str("fetch cancelled"));
+        }
+        tried.next(&config, url, username, allowed)
+    });
+    callbacks.certificate_check(|_, _| {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(git2::Error::from_str("fetch cancelled"));
+        }
+        // Leave the verdict to libgit2's own certificate validation
+        Ok(CertificateCheckStatus::CertificatePassthrough)
+    });
+    callbacks.sideband_progress(|data| {
+        log::trace!("remote: {}", String::from_utf8_lossy(data).trim_end());
+        !cancelled.load(Ordering::Relaxed)
+    });
+    callbacks.transfer_progress(|_| !cancelled.load(Ordering::Relaxed));
+
+    let mut proxy = ProxyOptions::new();
+    proxy.auto();
+    let mut options = FetchOptions::new();
+    options
+        .remote_callbacks(callbacks)
+        .proxy_options(proxy)
+        .download_tags(AutotagOption::Auto);
+
+    remote
+        .fetch::<&str>(&[], Some(&mut options), None)
+        .with_context(|| format!("Failed to fetch from {remote_name}"))?;
+
+    let stats = remote.stats();
+    log::debug!(
+        "Fetched {} objects ({} bytes) from {remote_name} in {}",
+        stats.received_objects(),
+        stats.received_bytes(),
+        repo.path().display()
+    );
+    Ok(())
+}
+
+/// Tracks which credentials have already been offered, since libgit2 calls the
+/// credentials callback again after every rejected attempt.
+#[derive(Default)]
+struct TriedCredentials {
+    /// The SSH agent has been tried.
+    agent: bool,
+    /// Number of entries of [`SSH_KEY_FILES`] that have been tried.
+    key_files: usize,
+    /// The git credential helpers have been tried.
+    helper: bool,
+    /// The default (e.g. NTLM, Kerberos) credentials have been tried.
+    default: bool,
+}
+
+impl TriedCredentials {
+    /// Returns the next credentials to try for the given URL.
+    fn next(
+        &mut self,
+        config: &git2::Config,
+        url: &str,
+        username: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        let user = username.unwrap_or("git");
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(user);
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            if !self.agent {
+                self.agent = true;
+                if let Ok(cred) = Cred::ssh_key_from_agent(user) {
+                    return Ok(cred);
+                }
+            }
+            let ssh_dir = home_dir().map(|home| home.join(".ssh"));
+            while let Some(ssh_dir) = &ssh_dir
+                && let Some(file) = SSH_KEY_FILES.get(self.key_files)
+            {
+                self.key_files += 1;
+                let private_key = ssh_dir.join(file);
+                if private_key.is_file() {
+                    let public_key = private_key.with_extension("pub");
+                    let public_key = public_key.is_file().then_some(public_key);
+                    return Cred::ssh_key(user, public_key.as_deref(), &private_key, None);
+                }
+            }
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !self.helper {
+            self.helper = true;
+            if let Ok(cred) = Cred::credential_helper(config, url, username) {
+                return Ok(cred);
+            }
+        }
+        if allowed.contains(CredentialType::DEFAULT) && !self.default {
+            self.default = true;
+            return Cred::default();
+        }
+        Err(git2::Error::from_str(&format!(
+            "no valid credentials available for {url}"
+        )))
+    }
+}
+
+/// Returns the home directory of the current user.
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
@@ -1,93 +1,439 @@
-use std::path::Path;
-use std::process::Command;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
-use git2::{Repository, StatusOptions};
+use git2::{Repository, RepositoryState, Status, StatusOptions};
+use serde::Serialize;
+
+/// Number of first-parent steps searched from each ref when describing a detached HEAD.
+const DESCRIBE_DEPTH_LIMIT: usize = 1000;
+
+/// Number of commits after which counting the history of a branch stops.
+pub const COMMIT_COUNT_CAP: usize = 10_000;
 
 /// Holds information about a Git repository for status display.
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct RepoInfo {
     /// The directory name of the repository.
     pub name: String,
+    /// The path to the repository directory.
+    pub path: PathBuf,
     /// The current branch name.
     pub branch: String,
     /// Number of commits ahead of upstream.
     pub ahead: usize,
     /// Number of commits behind upstream.
     pub behind: usize,
-    /// Total number of commits in the current branch.
-    pub commits: usize,
+    /// Total number of commits in the current branch, if they were counted.
+    /// Counting stops after [`COMMIT_COUNT_CAP`] commits, so any larger value means "more than the cap".
+    pub commits: Option<usize>,
+    /// Time of the HEAD commit in seconds since the Unix epoch, if there is one.
+    pub last_commit_time: Option<i64>,
+    /// Author name of the HEAD commit, if there is one.
+    pub last_commit_author: Option<String>,
+    /// Summary (first line of the message) of the HEAD commit, if there is one.
+    pub last_commit_summary: Option<String>,
     /// Number of untracked files.
     pub untracked: usize,
     /// Number of changed (unstaged or staged) files.
     pub changed: usize,
+    /// Number of files per kind of change.
+    pub files: FileCounts,
+    /// Number of stashed changes.
+    pub stashes: usize,
     /// Status string: "Clean", "Dirty", or "?".
     pub status: String,
-    /// True if there are unpushed commits.
+    /// Operation that was started but not finished, e.g. a rebase with conflicts.
+    pub operation: Option<Operation>,
+    /// True if there are unpushed commits on the current or any other local branch.
     pub has_unpushed: bool,
-    /// Remote URL (if available).
+    /// Local branches with commits that are not pushed.
+    pub unpushed_branches: Vec<UnpushedBranch>,
+    /// Whether the current branch tracks an upstream branch that still exists.
+    pub upstream: UpstreamState,
+    /// Short name of the configured upstream branch, e.g. "origin/main", even if it is gone.
+    pub upstream_name: Option<String>,
+    /// Divergence of HEAD from the remote's default branch, if the remote has a `HEAD` ref.
+    pub default_branch: Option<DefaultBranch>,
+    /// True if HEAD is detached.
+    pub detached: bool,
+    /// True if fetching timed out; the remaining information reflects the previous remote state.
+    pub fetch_timed_out: bool,
+    /// Remote URL of "origin" (if available).
     pub remote_url: Option<String>,
+    /// All configured remotes with their URLs.
+    pub remotes: Vec<RemoteInfo>,
+    /// Linked worktrees of the repository; empty if the repository is itself a linked worktree.
+    pub worktrees: Vec<WorktreeInfo>,
+}
+
+/// Number of files in the working directory and index per kind of change.
+/// A file can be counted in several kinds, e.g. a staged and then modified file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FileCounts {
+    /// Files with changes in the index.
+    pub staged: usize,
+    /// Tracked files with changes in the working directory that are not staged.
+    pub unstaged: usize,
+    /// Files with merge conflicts.
+    pub conflicted: usize,
+    /// Files renamed in the index.
+    pub renamed: usize,
+    /// Deleted files, staged or not.
+    pub deleted: usize,
+    /// Untracked files.
+    pub untracked: usize,
+}
+
+impl FileCounts {
+    /// Counts the kinds of changes of the given status entries.
+    pub fn from_statuses(statuses: &git2::Statuses<'_>) -> Self {
+        let mut counts = Self::default();
+        for entry in statuses.iter() {
+            counts.add(entry.status());
+        }
+        counts
+    }
+
+    /// Adds a single file with the given status.
+    fn add(&mut self, status: Status) {
+        if status.is_conflicted() {
+            self.conflicted += 1;
+            return;
+        }
+        if status.is_wt_new() {
+            self.untracked += 1;
+        }
+        if status.intersects(
+            Status::INDEX_NEW
+                | Status::INDEX_MODIFIED
+                | Status::INDEX_DELETED
+                | Status::INDEX_RENAMED
+                | Status::INDEX_TYPECHANGE,
+        ) {
+            self.staged += 1;
+        }
+        if status.intersects(
+            Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE,
+        ) {
+            self.unstaged += 1;
+        }
+        if status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED) {
+            self.renamed += 1;
+        }
+        if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+            self.deleted += 1;
+        }
+    }
+}
+
+impl fmt::Display for FileCounts {
+    /// Writes the non-zero counts compactly, e.g. "+3 ~2 -1 !1 ?4":
+    /// `+` staged, `~` unstaged, `>` renamed, `-` deleted, `!` conflicted, `?` untracked.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            ('+', self.staged),
+            ('~', self.unstaged),
+            ('>', self.renamed),
+            ('-', self.deleted),
+            ('!', self.conflicted),
+            ('?', self.untracked),
+        ];
+        let mut first = true;
+        for (symbol, count) in parts {
+            if count > 0 {
+                if !first {
+                    f.write_str(" ")?;
+                }
+                write!(f, "{symbol}{count}")?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A local branch with commits that are not pushed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct UnpushedBranch {
+    /// Name of the local branch.
+    pub name: String,
+    /// Short name of the upstream the branch was compared with, or `None` if it has
+    /// no (existing) upstream and was compared with all remote-tracking branches.
+    pub upstream: Option<String>,
+    /// Number of commits that are not pushed. Commits on no remote are counted up to
+    /// [`COMMIT_COUNT_CAP`], so any larger value means "more than the cap".
+    pub unpushed: usize,
+}
+
+/// Divergence of HEAD from the default branch of a remote, e.g. "origin/main".
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DefaultBranch {
+    /// Short name of the remote-tracking default branch, e.g. "origin/main".
+    pub name: String,
+    /// Number of commits on HEAD that are not on the default branch.
+    pub ahead: usize,
+    /// Number of commits on the default branch that are not on HEAD.
+    pub behind: usize,
+}
+
+/// Relation of the current branch to its upstream branch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamState {
+    /// The branch tracks an existing upstream branch.
+    Tracking,
+    /// No upstream is configured, or HEAD is detached.
+    None,
+    /// An upstream is configured, but its ref no longer exists, e.g. because the
+    /// branch was deleted on the remote and pruned.
+    Gone,
+}
+
+/// An operation in progress in the repository, as reported by [`Repository::state`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// A merge stopped before committing, e.g. because of conflicts.
+    Merge,
+    /// A rebase, interactive or not.
+    Rebase,
+    /// A cherry-pick of one or more commits.
+    CherryPick,
+    /// A revert of one or more commits.
+    Revert,
+    /// A bisect session.
+    Bisect,
+    /// Applying patches from a mailbox with `git am`.
+    ApplyMailbox,
+}
+
+impl Operation {
+    /// Returns the operation in progress for the given repository state, if any.
+    pub const fn from_state(state: RepositoryState) -> Option<Self> {
+        match state {
+            RepositoryState::Clean => None,
+            RepositoryState::Merge => Some(Self::Merge),
+            RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge => Some(Self::Rebase),
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                Some(Self::CherryPick)
+            }
+            RepositoryState::Revert | RepositoryState::RevertSequence => Some(Self::Revert),
+            RepositoryState::Bisect => Some(Self::Bisect),
+            RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => {
+                Some(Self::ApplyMailbox)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Merge => "Merge",
+            Self::Rebase => "Rebase",
+            Self::CherryPick => "Cherry-pick",
+            Self::Revert => "Revert",
+            Self::Bisect => "Bisect",
+            Self::ApplyMailbox => "Apply mailbox",
+        })
+    }
+}
+
+/// A configured remote of a repository.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RemoteInfo {
+    /// Name of the remote, e.g. "origin".
+    pub name: String,
+    /// URL of the remote, if it is valid UTF-8.
+    pub url: Option<String>,
+}
+
+/// A linked worktree of a repository, as created by `git worktree add`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct WorktreeInfo {
+    /// Name of the worktree, usually the directory name it was created with.
+    pub name: String,
+    /// Path of the worktree's working directory.
+    pub path: PathBuf,
+    /// Branch checked out in the worktree, or `None` if it cannot be opened.
+    pub branch: Option<String>,
+    /// Status string: "Clean", "Dirty", or "?"; `None` if the worktree cannot be opened
+    /// or its status was not requested, since reading it walks the worktree's files.
+    pub status: Option<String>,
+    /// True if the worktree is locked, e.g. because it is on a removable drive.
+    pub locked: bool,
+    /// Reason given when the worktree was locked, if any.
+    pub lock_reason: Option<String>,
+    /// True if the working directory is gone, so `git worktree prune` would remove the worktree.
+    pub prunable: bool,
+}
+
+/// Stage of the repository scan that failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureStage {
+    /// The repository could not be opened.
+    Open,
+    /// Fetching from the remote failed.
+    Fetch,
+    /// Walking the commit history failed.
+    Revwalk,
+    /// Reading the working directory status failed.
+    Status,
+}
+
+impl fmt::Display for FailureStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Open => "open",
+            Self::Fetch => "fetch",
+            Self::Revwalk => "revwalk",
+            Self::Status => "status",
+        })
+    }
+}
+
+/// A repository that could not be processed, with the reason of the failure.
+#[derive(Clone, Debug, Serialize)]
+pub struct FailedRepo {
+    /// The directory name of the repository.
+    pub name: String,
+    /// The path to the repository directory.
+    pub path: PathBuf,
+    /// The stage of the scan that failed.
+    pub stage: FailureStage,
+    /// The underlying git2 or IO error.
+    pub error: String,
+}
+
+impl FailedRepo {
+    /// Creates a new `FailedRepo` for the repository at `path`.
+    pub fn new(path: &Path, stage: FailureStage, error: &anyhow::Error) -> Self {
+        Self {
+            name: repo_name(path),
+            path: path.to_path_buf(),
+            stage,
+            error: format!("{error:#}"),
+        }
+    }
+}
+
+impl fmt::Display for FailedRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {} failed: {}",
+            self.name,
+            self.path.display(),
+            self.stage,
+            self.error
+        )
+    }
+}
+
+/// Returns the directory name of the repository at `path`.
+fn repo_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
 }
 
 impl RepoInfo {
     /// Creates a new `RepoInfo` instance.
     /// # Arguments
-    /// * `repo` - The Git repository to gather information from.
-    /// * `show_remote` - Whether to include the remote URL in the info.
-    /// * `fetch` - Whether to run a fetch operation before gathering info.
+    /// * `repo` - The Git repository to gather information from. It is borrowed mutably
+    ///   because iterating the stashes requires it.
+    /// * `show_remote` - Whether to include the remote URLs in the info.
+    /// * `count_commits` - Whether to count the commits of the current branch, which walks its history.
+    /// * `worktree_status` - Whether to read the status of the linked worktrees, which walks their files.
     /// * `path` - The path to the repository directory.
     ///
     /// # Returns
     /// A `RepoInfo` instance containing the repository's status information.
     ///
     /// # Errors
-    /// Returns an error if the repository cannot be opened, or if fetching fails.
-    /// If `fetch` is true, it will attempt to fetch from the "origin"
-    /// remote to update upstream information.
-    /// If fetching fails, it will use that error to return an error.
+    /// Returns a [`FailedRepo`] describing the failed stage if walking
+    /// the history or reading the status fails.
     pub fn new(
-        repo: &Repository,
+        repo: &mut Repository,
         show_remote: bool,
-        fetch: bool,
+        count_commits: bool,
+        worktree_status: bool,
         path: &Path,
-    ) -> anyhow::Result<Self> {
-        if fetch {
-            // Attempt to fetch from origin, ignoring errors
-            fetch_origin(repo)?;
-        }
+    ) -> Result<Self, FailedRepo> {
+        let fail = |stage| move |e| FailedRepo::new(path, stage, &e);
         let branch = get_branch_name(repo);
         let (ahead, behind) = get_ahead_behind(repo);
-        let commits = get_total_commits(repo)?;
-        let untracked = get_untracked_count(repo);
-        let changed = get_changed_count(repo).saturating_add(untracked);
-        let status = get_repo_status(repo);
-        let has_unpushed = ahead > 0;
-        let remote_url = if show_remote {
-            get_remote_url(repo)
+        let commits = if count_commits {
+            Some(get_total_commits(repo).map_err(fail(FailureStage::Revwalk))?)
         } else {
             None
         };
-        let name = path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
+        let last_commit_time = get_last_commit_time(repo);
+        let last_commit_author = get_last_commit_author(repo);
+        let last_commit_summary = get_last_commit_summary(repo);
+        // Walking the working tree is the most expensive part of the scan,
+        // so every status-derived field is computed from the same pass.
+        let statuses = read_statuses(repo).map_err(fail(FailureStage::Status))?;
+        let untracked = get_untracked_count(&statuses);
+        let changed = get_changed_count(&statuses).saturating_add(untracked);
+        let files = FileCounts::from_statuses(&statuses);
+        let status = get_repo_status(&statuses);
+        drop(statuses);
+        let stashes = get_stash_count(repo);
+        let operation = Operation::from_state(repo.state());
+        let unpushed_branches = get_unpushed_branches(repo);
+        let has_unpushed = ahead > 0 || !unpushed_branches.is_empty();
+        let (upstream, upstream_name) = get_upstream(repo);
+        let default_branch = get_default_branch(repo);
+        let detached = repo.head_detached().unwrap_or(false);
+        let (remote_url, remotes) = if show_remote {
+            (get_remote_url(repo), get_remotes(repo))
+        } else {
+            (None, Vec::new())
+        };
+        let worktrees = get_worktrees(repo, worktree_status);
         Ok(Self {
-            name,
+            name: repo_name(path),
+            path: path.to_path_buf(),
             branch,
             ahead,
             behind,
             commits,
+            last_commit_time,
+            last_commit_author,
+            last_commit_summary,
             untracked,
             changed,
+            files,
+            stashes,
             status,
+            operation,
             has_unpushed,
+            unpushed_branches,
+            upstream,
+            upstream_name,
+            default_branch,
+            detached,
+            fetch_timed_out: false,
             remote_url,
+            remotes,
+            worktrees,
         })
     }
 }
 
 /// Returns the current branch name or a fallback if not available.
+/// A detached HEAD is described as e.g. "detached at 1a2b3c4 (main~2)", see [`describe_detached_head`].
 pub fn get_branch_name(repo: &Repository) -> String {
+    if repo.head_detached().unwrap_or(false)
+        && let Some(description) = describe_detached_head(repo)
+    {
+        return format!("detached at {description}");
+    }
     if let Ok(head) = repo.head() {
         if let Some(name) = head.shorthand() {
             return name.to_owned();
@@ -106,6 +452,68 @@ pub fn get_branch_name(repo: &Repository) -> String {
     "(no branch)".to_owned()
 }
 
+/// Describes the commit of a detached HEAD by its short id and, if there is one,
+/// the nearest branch (or tag, if no branch does) containing it on its first-parent line,
+/// e.g. "1a2b3c4 (main~2)" or "1a2b3c4 (v1.0)".
+pub fn describe_detached_head(repo: &Repository) -> Option<String> {
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    let short_id = commit.as_object().short_id().ok()?.as_str()?.to_owned();
+    Some(match nearest_containing_ref(repo, commit.id()) {
+        Some(name) => format!("{short_id} ({name})"),
+        None => short_id,
+    })
+}
+
+/// Returns the branch or remote-tracking branch closest to `oid` among those containing it,
+/// with the number of first-parent steps between them as `~n` suffix, so the result is a
+/// valid revision naming `oid`. Tags are only considered if no branch contains `oid`.
+fn nearest_containing_ref(repo: &Repository, oid: git2::Oid) -> Option<String> {
+    let references = repo
+        .references()
+        .ok()?
+        .flatten()
+        .filter(|r| r.shorthand().is_some_and(|name| !name.ends_with("/HEAD")))
+        .collect::<Vec<_>>();
+    let nearest = |tags: bool| {
+        references
+            .iter()
+            .filter(|r| {
+                if tags {
+                    r.is_tag()
+                } else {
+                    r.is_branch() || r.is_remote()
+                }
+            })
+            .filter_map(|r| {
+                let commit = r.peel_to_commit().ok()?;
+                let distance = first_parent_distance(commit, oid)?;
+                Some((distance, r.shorthand()?.to_owned()))
+            })
+            .min()
+    };
+    nearest(false)
+        .or_else(|| nearest(true))
+        .map(|(distance, name)| {
+            if distance == 0 {
+                name
+            } else {
+                format!("{name}~{distance}")
+            }
+        })
+}
+
+/// Returns the number of first-parent steps from `commit` back to `oid`, or `None` if `oid`
+/// is not on the first-parent chain within [`DESCRIBE_DEPTH_LIMIT`] steps.
+fn first_parent_distance(mut commit: git2::Commit<'_>, oid: git2::Oid) -> Option<usize> {
+    for distance in 0..=DESCRIBE_DEPTH_LIMIT {
+        if commit.id() == oid {
+            return Some(distance);
+        }
+        commit = commit.parent(0).ok()?;
+    }
+    None
+}
+
 /// Returns (ahead, behind) tuple for the current branch vs. its upstream.
 pub fn get_ahead_behind(repo: &Repository) -> (usize, usize) {
     let Ok(head) = repo.head() else { return (0, 0) };
@@ -125,70 +533,240 @@ pub fn get_ahead_behind(repo: &Repository) -> (usize, usize) {
     (0, 0)
 }
 
-/// Returns the total number of commits in the current branch.
+/// Returns the upstream state of the current branch and the short name of its configured upstream.
+pub fn get_upstream(repo: &Repository) -> (UpstreamState, Option<String>) {
+    let Some(upstream) = repo
+        .head()
+        .ok()
+        .filter(git2::Reference::is_branch)
+        .and_then(|head| head.name().map(str::to_owned))
+        .and_then(|name| repo.branch_upstream_name(&name).ok())
+    else {
+        return (UpstreamState::None, None);
+    };
+    let Some(upstream) = upstream.as_str() else {
+        return (UpstreamState::None, None);
+    };
+    let state = if repo.find_reference(upstream).is_ok() {
+        UpstreamState::Tracking
+    } else {
+        UpstreamState::Gone
+    };
+    let name = upstream
+        .strip_prefix("refs/remotes/")
+        .or_else(|| upstream.strip_prefix("refs/heads/"))
+        .unwrap_or(upstream);
+    (state, Some(name.to_owned()))
+}
+
+/// Returns how far HEAD has diverged from the default branch of a remote.
+///
+/// The default branch is resolved via `refs/remotes/<remote>/HEAD`, trying the remote of
+/// the current branch's upstream first, then "origin", then the other remotes.
+/// Returns `None` if HEAD has no commits or no remote has a `HEAD` ref,
+/// e.g. because the repository was not cloned and `git remote set-head` was never run.
+pub fn get_default_branch(repo: &Repository) -> Option<DefaultBranch> {
+    let head = repo.head().ok()?.target()?;
+    let upstream_remote = repo
+        .head()
+        .ok()
+        .filter(git2::Reference::is_branch)
+        .and_then(|head| head.name().map(str::to_owned))
+        .and_then(|name| repo.branch_upstream_remote(&name).ok())
+        .and_then(|remote| remote.as_str().map(str::to_owned));
+    let remotes = repo.remotes().ok()?;
+    let candidates = upstream_remote
+        .into_iter()
+        .chain(std::iter::once("origin".to_owned()))
+        .chain(remotes.iter().flatten().map(str::to_owned));
+    for remote in candidates {
+        let Ok(reference) = repo.find_reference(&format!("refs/remotes/{remote}/HEAD")) else {
+            continue;
+        };
+        let Some(target) = reference.resolve().ok().and_then(|r| r.target()) else {
+            continue;
+        };
+        let name = reference
+            .symbolic_target()
+            .and_then(|target| target.strip_prefix("refs/remotes/"))
+            .map_or_else(|| format!("{remote}/HEAD"), str::to_owned);
+        return match repo.graph_ahead_behind(head, target) {
+            Ok((ahead, behind)) => Some(DefaultBranch {
+                name,
+                ahead,
+                behind,
+            }),
+            Err(e) => {
+                log::debug!("Failed to compare HEAD with {name}: {e}");
+                None
+            }
+        };
+    }
+    None
+}
+
+/// Returns every local branch with commits that are not pushed, sorted by name.
+///
+/// A branch with an upstream is compared with it. A branch without upstream, or whose
+/// upstream is gone, is compared with all remote-tracking branches, so only commits
+/// that exist on no remote at all count as unpushed.
+pub fn get_unpushed_branches(repo: &Repository) -> Vec<UnpushedBranch> {
+    let Ok(branches) = repo.branches(Some(git2::BranchType::Local)) else {
+        return Vec::new();
+    };
+    let mut unpushed = branches
+        .flatten()
+        .filter_map(|(branch, _)| {
+            let name = branch.name().ok()??.to_owned();
+            let local = branch.get().target()?;
+            let upstream = branch.upstream().ok();
+            let upstream_name = upstream
+                .as_ref()
+                .and_then(|u| u.name().ok().flatten().map(str::to_owned));
+            let count = upstream.and_then(|u| u.get().target()).map_or_else(
+                || count_not_on_any_remote(repo, local),
+                |upstream| repo.graph_ahead_behind(local, upstream).map(|(a, _)| a),
+            );
+            match count {
+                Ok(0) => None,
+                Ok(count) => Some(UnpushedBranch {
+                    name,
+                    upstream: upstream_name,
+                    unpushed: count,
+                }),
+                Err(e) => {
+                    log::debug!("Failed to compare branch {name} with its remote: {e}");
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    unpushed.sort_by(|a, b| a.name.cmp(&b.name));
+    unpushed
+}
+
+/// Counts the commits reachable from `oid` that are on no remote-tracking branch, counting at most
+/// one more than [`COMMIT_COUNT_CAP`] so that a long local-only history is not walked completely.
+fn count_not_on_any_remote(repo: &Repository, oid: git2::Oid) -> Result<usize, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(oid)?;
+    for remote in repo.branches(Some(git2::BranchType::Remote))?.flatten() {
+        if let Some(target) = remote.0.get().target() {
+            revwalk.hide(target)?;
+        }
+    }
+    Ok(revwalk.take(COMMIT_COUNT_CAP.saturating_add(1)).count())
+}
+
+/// Returns the total number of commits in the current branch, counting at most
+/// one more than [`COMMIT_COUNT_CAP`].
 pub fn get_total_commits(repo: &Repository) -> anyhow::Result<usize> {
+    count_commits(repo, COMMIT_COUNT_CAP)
+}
+
+/// Returns the number of commits in the current branch, stopping after `cap + 1` commits
+/// so that huge histories are not walked completely.
+///
+/// # Errors
+/// Returns an error if the history cannot be walked.
+pub fn count_commits(repo: &Repository, cap: usize) -> anyhow::Result<usize> {
     let Ok(head) = repo.head() else { return Ok(0) };
     let Some(oid) = head.target() else {
         return Ok(0);
     };
     let mut revwalk = repo.revwalk()?;
     revwalk.push(oid)?;
-    Ok(revwalk.count())
+    Ok(revwalk.take(cap.saturating_add(1)).count())
 }
 
-/// Returns the number of untracked files in the working directory.
-pub fn get_untracked_count(repo: &Repository) -> usize {
-    let mut opts = StatusOptions::new();
-    opts.include_untracked(true);
-    repo.statuses(Some(&mut opts))
-        .map(|statuses| statuses.iter().filter(|e| e.status().is_wt_new()).count())
-        .unwrap_or(0)
+/// Returns the time of the HEAD commit in seconds since the Unix epoch.
+pub fn get_last_commit_time(repo: &Repository) -> Option<i64> {
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.time().seconds())
+}
+
+/// Returns the author name of the HEAD commit.
+pub fn get_last_commit_author(repo: &Repository) -> Option<String> {
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    commit.author().name().map(str::to_owned)
+}
+
+/// Returns the summary (first line of the message) of the HEAD commit.
+pub fn get_last_commit_summary(repo: &Repository) -> Option<String> {
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    commit.summary().map(str::to_owned)
 }
 
-/// Returns the number of changed (unstaged or staged) files.
-pub fn get_changed_count(repo: &Repository) -> usize {
+/// Reads the status of the working directory and index in a single pass,
+/// including untracked files and staged renames but no ignored files.
+/// All status-derived information is computed from its result.
+///
+/// Renames in the working directory are not detected: it is the most expensive status option,
+/// and like `git status` an unstaged move is reported as a deleted and an untracked file.
+///
+/// # Errors
+/// Returns an error if the status of the working directory cannot be read.
+pub fn read_statuses(repo: &Repository) -> anyhow::Result<git2::Statuses<'_>> {
     let mut opts = StatusOptions::new();
-    opts.include_untracked(false);
-    repo.statuses(Some(&mut opts))
-        .map(|statuses| {
-            statuses
-                .iter()
-                .filter(|e| {
-                    let s = e.status();
-                    s.is_wt_modified()
-                        || s.is_index_modified()
-                        || s.is_wt_deleted()
-                        || s.is_index_deleted()
-                        || s.is_conflicted()
-                })
-                .count()
+    opts.include_untracked(true)
+        .include_ignored(false)
+        .renames_head_to_index(true);
+    Ok(repo.statuses(Some(&mut opts))?)
+}
+
+/// Returns the number of untracked files in the working directory.
+pub fn get_untracked_count(statuses: &git2::Statuses<'_>) -> usize {
+    statuses.iter().filter(|e| e.status().is_wt_new()).count()
+}
+
+/// Returns the number of changed (unstaged or staged) tracked files.
+pub fn get_changed_count(statuses: &git2::Statuses<'_>) -> usize {
+    statuses
+        .iter()
+        .filter(|e| {
+            e.status().intersects(
+                Status::WT_MODIFIED
+                    | Status::INDEX_MODIFIED
+                    | Status::WT_DELETED
+                    | Status::INDEX_DELETED
+                    | Status::WT_RENAMED
+                    | Status::INDEX_RENAMED
+                    | Status::CONFLICTED,
+            )
         })
-        .unwrap_or(0)
+        .count()
 }
 
-/// Returns the status string for the repository: "Clean", "Dirty", or "?".
-pub fn get_repo_status(repo: &Repository) -> String {
-    let mut opts = StatusOptions::new();
-    opts.include_untracked(true).include_ignored(false);
-    repo.statuses(Some(&mut opts)).map_or_else(
-        |_| "?".to_owned(),
-        |statuses| {
-            if statuses.iter().all(|e| {
-                e.status().is_ignored()
-                    || !e.status().is_wt_new()
-                        && !e.status().is_index_new()
-                        && !e.status().is_wt_modified()
-                        && !e.status().is_index_modified()
-                        && !e.status().is_wt_deleted()
-                        && !e.status().is_index_deleted()
-                        && !e.status().is_conflicted()
-            }) {
-                "Clean".to_owned()
-            } else {
-                "Dirty".to_owned()
-            }
-        },
-    )
+/// Returns the number of entries in the stash (`refs/stash`).
+pub fn get_stash_count(repo: &mut Repository) -> usize {
+    let mut count = 0;
+    if let Err(e) = repo.stash_foreach(|_, _, _| {
+        count += 1;
+        true
+    }) {
+        log::debug!(
+            "Failed to read the stashes of {}: {e}",
+            repo.path().display()
+        );
+    }
+    count
+}
+
+/// Returns the status string for the repository: "Clean" or "Dirty".
+pub fn get_repo_status(statuses: &git2::Statuses<'_>) -> String {
+    let clean = statuses.iter().all(|e| {
+        e.status().is_ignored()
+            || !e.status().is_wt_new()
+                && !e.status().is_index_new()
+                && !e.status().is_wt_modified()
+                && !e.status().is_index_modified()
+                && !e.status().is_wt_deleted()
+                && !e.status().is_index_deleted()
+                && !e.status().is_index_renamed()
+                && !e.status().is_wt_renamed()
+                && !e.status().is_conflicted()
+    });
+    if clean { "Clean" } else { "Dirty" }.to_owned()
 }
 
 /// Returns the remote URL for "origin", if available.
@@ -198,24 +776,90 @@ pub fn get_remote_url(repo: &Repository) -> Option<String> {
         .and_then(|r| r.url().map(std::borrow::ToOwned::to_owned))
 }
 
-/// Executes a fetch operation for the "origin" remote to update upstream information.
-pub fn fetch_origin(repo: &Repository) -> anyhow::Result<()> {
-    let path = repo
-        .path()
-        .parent()
-        .ok_or_else(|| anyhow::anyhow!("No parent directory found"))?;
-    let output = Command::new("git")
-        .arg("fetch")
-        .arg("origin")
-        .current_dir(path)
-        .output()?;
-
-    if !output.status.success() {
-        anyhow::bail!(
-            "Failed to fetch from origin: {}",
-            String::from_utf8_lossy(&output.stderr)
-        )
+/// Returns all configured remotes with their URLs, in configuration order.
+pub fn get_remotes(repo: &Repository) -> Vec<RemoteInfo> {
+    let Ok(names) = repo.remotes() else {
+        return Vec::new();
+    };
+    names
+        .iter()
+        .flatten()
+        .map(|name| RemoteInfo {
+            name: name.to_owned(),
+            url: repo
+                .find_remote(name)
+                .ok()
+                .and_then(|r| r.url().map(str::to_owned)),
+        })
+        .collect()
+}
+
+/// Returns the linked worktrees of the repository, sorted by name.
+///
+/// A linked worktree has no worktrees of its own, so this is empty for it.
+/// Worktrees whose directory is gone are still returned, marked as prunable
+/// unless they are locked. Their status is only read if `with_status` is set.
+pub fn get_worktrees(repo: &Repository, with_status: bool) -> Vec<WorktreeInfo> {
+    if repo.is_worktree() {
+        return Vec::new();
+    }
+    let Ok(names) = repo.worktrees() else {
+        return Vec::new();
+    };
+    let mut worktrees = names
+        .iter()
+        .flatten()
+        .filter_map(|name| match repo.find_worktree(name) {
+            Ok(worktree) => Some(worktree_info(name, &worktree, with_status)),
+            Err(e) => {
+                log::debug!("Failed to open worktree {name}: {e}");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    worktrees.sort_by(|a, b| a.name.cmp(&b.name));
+    worktrees
+}
+
+/// Gathers the branch and state of a single linked worktree.
+fn worktree_info(name: &str, worktree: &git2::Worktree, with_status: bool) -> WorktreeInfo {
+    let (locked, lock_reason) = match worktree.is_locked() {
+        Ok(git2::WorktreeLockStatus::Locked(reason)) => (true, reason.filter(|r| !r.is_empty())),
+        Ok(git2::WorktreeLockStatus::Unlocked) | Err(_) => (false, None),
+    };
+    let opened = worktree
+        .validate()
+        .and_then(|()| Repository::open_from_worktree(worktree))
+        .ok();
+    let (branch, status) = opened.map_or((None, None), |repo| {
+        let status = with_status.then(|| {
+            read_statuses(&repo)
+                .map_or_else(|_| "?".to_owned(), |statuses| get_repo_status(&statuses))
+        });
+        (Some(get_branch_name(&repo)), status)
+    });
+    WorktreeInfo {
+        name: name.to_owned(),
+        path: worktree.path().to_path_buf(),
+        branch,
+        status,
+        locked,
+        lock_reason,
+        prunable: worktree.is_prunable(None).unwrap_or(false),
     }
+}
 
-    Ok(())
+/// Returns the working directory of the main repository if `repo` is a linked worktree.
+///
+/// Returns `None` for a main repository, and for a worktree of a bare repository,
+/// which has no working directory of its own.
+pub fn main_worktree_path(repo: &Repository) -> Option<PathBuf> {
+    if !repo.is_worktree() {
+        return None;
+    }
+    let common = repo.commondir();
+    if common.file_name()? != ".git" {
+        return None;
+    }
+    common.parent().map(Path::to_path_buf)
 }
@@ -1,32 +1,90 @@
+use std::process::ExitCode;
+
 use anyhow::Result;
 use clap::Parser as _;
-use cli::Args;
+use cli::{Args, OutputFormat};
+use util::ScanResult;
 
 mod cli;
+mod fetch;
 mod gitinfo;
 mod printer;
+mod progress;
 #[cfg(test)]
 mod tests;
 mod util;
 
 /// Entry point for the git-statuses CLI tool.
 /// Parses arguments, scans for repositories, prints their status and a summary.
-fn main() -> Result<()> {
+/// With `--check`, the exit code reports which repositories need attention.
+fn main() -> Result<ExitCode> {
     util::initialize_logger()?;
 
-    let args = Args::parse();
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            e.print()?;
+            return Ok(ExitCode::from(util::parse_error_exit_code(&e)));
+        }
+    };
     if args.legend {
         printer::print_legend();
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
-    let (mut repos, failed_repos) = util::find_repositories(&args)?;
+    let (mut repos, failed_repos) = if args.format == OutputFormat::Ndjson {
+        util::find_repositories_with(&args, |result| {
+            if let ScanResult::Repo(repo) = result
+                && !util::is_selected(repo, &args)
+            {
+                return;
+            }
+            printer::print_ndjson_result(result);
+        })
+    } else {
+        util::find_repositories(&args)
+    };
+    repos.retain(|repo| util::is_selected(repo, &args));
+    util::sort_repositories(&mut repos, args.sort, args.reverse);
 
-    printer::repositories_table(&mut repos, &args);
-    printer::failed_summary(&failed_repos);
-    if args.summary {
-        printer::summary(&repos, failed_repos.len());
+    match args.format {
+        OutputFormat::Table => {
+            printer::repositories_table(&repos, &args);
+            if args.details {
+                printer::write_unpushed_details(std::io::stdout().lock(), &repos)?;
+                printer::write_worktree_details(std::io::stdout().lock(), &repos)?;
+            }
+            printer::failed_summary(&failed_repos);
+            if args.summary {
+                printer::summary(&repos, failed_repos.len());
+            }
+        }
+        OutputFormat::Json => printer::print_json(&repos, &failed_repos)?,
+        OutputFormat::Ndjson => printer::print_ndjson_summary(&repos, failed_repos.len())?,
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let delimiter = if args.format == OutputFormat::Csv {
+                b','
+            } else {
+                b'\t'
+            };
+            printer::write_delimited(std::io::stdout().lock(), &repos, &args, delimiter)?;
+            printer::failed_summary(&failed_repos);
+        }
+        OutputFormat::Markdown => {
+            printer::write_markdown(std::io::stdout().lock(), &repos, &args)?;
+            printer::failed_summary(&failed_repos);
+        }
+        OutputFormat::Html => {
+            printer::write_html(std::io::stdout().lock(), &repos, &args)?;
+            printer::failed_summary(&failed_repos);
+        }
     }
 
-    Ok(())
+    if args.check {
+        return Ok(ExitCode::from(util::check_exit_code(
+            &repos,
+            failed_repos.len(),
+        )));
+    }
+    Ok(ExitCode::SUCCESS)
 }
@@ -1,13 +1,592 @@
+use std::io::Write;
+
 use comfy_table::{Attribute, Cell, Color, ContentArrangement, Table, presets};
+use serde::Serialize;
+
+use crate::{
+    cli::{Args, Column},
+    gitinfo::{COMMIT_COUNT_CAP, DefaultBranch, FailedRepo, RepoInfo, UpstreamState, WorktreeInfo},
+    util::{self, ScanResult},
+};
+
+/// Version of the JSON documents emitted by `--format json` and `--format ndjson`.
+/// Bump it whenever a field is renamed, removed or changes its meaning.
+pub const JSON_SCHEMA_VERSION: u32 = 6;
+
+/// Aggregated counters of a repository scan.
+#[derive(Serialize)]
+pub struct Summary {
+    /// Number of successfully scanned repositories.
+    pub total: usize,
+    /// Number of repositories without changes.
+    pub clean: usize,
+    /// Number of repositories with changes.
+    pub dirty: usize,
+    /// Number of repositories with unpushed commits.
+    pub unpushed: usize,
+    /// Number of local branches with unpushed commits, over all repositories.
+    pub unpushed_branches: usize,
+    /// Number of repositories with stashed changes.
+    pub stashed: usize,
+    /// Number of repositories with a detached HEAD.
+    pub detached: usize,
+    /// Number of repositories that could not be processed.
+    pub failed: usize,
+}
+
+impl Summary {
+    /// Computes the summary counters for the given repositories.
+    pub fn new(repos: &[RepoInfo], failed: usize) -> Self {
+        Self {
+            total: repos.len(),
+            clean: repos.iter().filter(|r| r.status == "Clean").count(),
+            dirty: repos.iter().filter(|r| r.status == "Dirty").count(),
+            unpushed: repos.iter().filter(|r| r.has_unpushed).count(),
+            unpushed_branches: repos.iter().map(|r| r.unpushed_branches.len()).sum(),
+            stashed: repos.iter().filter(|r| r.stashes > 0).count(),
+            detached: repos.iter().filter(|r| r.detached).count(),
+            failed,
+        }
+    }
+}
+
+/// The JSON document emitted by `--format json`.
+#[derive(Serialize)]
+pub struct JsonReport<'a> {
+    /// Schema version, see [`JSON_SCHEMA_VERSION`].
+    pub version: u32,
+    /// All successfully scanned repositories.
+    pub repositories: &'a [RepoInfo],
+    /// Repositories that could not be processed.
+    pub failed: &'a [FailedRepo],
+    /// Aggregated counters of the scan.
+    pub summary: Summary,
+}
+
+impl<'a> JsonReport<'a> {
+    /// Creates a new report for the given scan results.
+    pub fn new(repos: &'a [RepoInfo], failed: &'a [FailedRepo]) -> Self {
+        Self {
+            version: JSON_SCHEMA_VERSION,
+            repositories: repos,
+            failed,
+            summary: Summary::new(repos, failed.len()),
+        }
+    }
+}
+
+/// Prints the scan results as a pretty-printed JSON document to stdout.
+///
+/// # Errors
+/// Returns an error if the report cannot be serialized.
+pub fn print_json(repos: &[RepoInfo], failed: &[FailedRepo]) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(&JsonReport::new(repos, failed))?;
+    println!("{json}");
+    Ok(())
+}
+
+/// A single line of the `--format ndjson` stream.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NdjsonRecord<'a> {
+    /// A successfully scanned repository.
+    Repository(&'a RepoInfo),
+    /// A repository that could not be processed.
+    Failed(&'a FailedRepo),
+    /// The final record of the stream.
+    Summary {
+        /// Schema version, see [`JSON_SCHEMA_VERSION`].
+        version: u32,
+        /// Aggregated counters of the scan.
+        #[serde(flatten)]
+        summary: Summary,
+    },
+}
+
+impl<'a> From<ScanResult<'a>> for NdjsonRecord<'a> {
+    fn from(result: ScanResult<'a>) -> Self {
+        match result {
+            ScanResult::Repo(repo) => Self::Repository(repo),
+            ScanResult::Failed(failed) => Self::Failed(failed),
+        }
+    }
+}
+
+/// Prints a single record of the NDJSON stream as one line to stdout.
+///
+/// # Errors
+/// Returns an error if the record cannot be serialized.
+pub fn print_ndjson_record(record: &NdjsonRecord<'_>) -> anyhow::Result<()> {
+    let line = serde_json::to_string(record)?;
+    println!("{line}");
+    Ok(())
+}
+
+/// Streams a scan result as an NDJSON record, logging serialization errors
+/// instead of aborting the scan.
+pub fn print_ndjson_result(result: ScanResult<'_>) {
+    if let Err(e) = print_ndjson_record(&result.into()) {
+        log::error!("Failed to write NDJSON record: {e}");
+    }
+}
+
+/// Prints the final summary record of the NDJSON stream.
+///
+/// # Errors
+/// Returns an error if the record cannot be serialized.
+pub fn print_ndjson_summary(repos: &[RepoInfo], failed: usize) -> anyhow::Result<()> {
+    print_ndjson_record(&NdjsonRecord::Summary {
+        version: JSON_SCHEMA_VERSION,
+        summary: Summary::new(repos, failed),
+    })
+}
+
+/// Returns the header of the given column.
+const fn header(column: Column) -> &'static str {
+    match column {
+        Column::Directory => "Directory",
+        Column::Path => "Path",
+        Column::Branch => "Branch",
+        Column::Upstream => "Upstream",
+        Column::Ahead => "Ahead",
+        Column::Behind => "Behind",
+        Column::DefaultBranch => "Default branch",
+        Column::Commits => "Commits",
+        Column::Untracked => "Untracked",
+        Column::Changed => "Changed",
+        Column::Files => "Files",
+        Column::Stash => "Stash",
+        Column::UnpushedBranches => "Unpushed branches",
+        Column::Age => "Age",
+        Column::Author => "Author",
+        Column::LastCommit => "Last commit",
+        Column::Status => "Status",
+        Column::Remote => "Remote",
+        Column::Remotes => "Remotes",
+        Column::Worktrees => "Worktrees",
+    }
+}
+
+/// Returns the text shown in the status column, e.g. "Dirty (3 changed)"
+/// or "Rebase in progress (1 changed)".
+fn status_text(repo: &RepoInfo) -> String {
+    let state = repo.operation.map_or_else(
+        || repo.status.clone(),
+        |operation| format!("{operation} in progress"),
+    );
+    let status = if repo.status == "Dirty" {
+        format!("{state} ({} changed)", repo.changed)
+    } else {
+        state
+    };
+    if repo.fetch_timed_out {
+        format!("{status} (fetch timed out)")
+    } else {
+        status
+    }
+}
+
+/// Returns the text shown in the upstream column, e.g. "origin/main" or "origin/feature (gone)".
+fn upstream_text(repo: &RepoInfo) -> String {
+    match (repo.upstream, &repo.upstream_name) {
+        (UpstreamState::Tracking, Some(name)) => name.clone(),
+        (UpstreamState::Gone, Some(name)) => format!("{name} (gone)"),
+        (UpstreamState::Gone, None) => "(gone)".to_owned(),
+        (UpstreamState::Tracking | UpstreamState::None, _) => "-".to_owned(),
+    }
+}
+
+/// Returns the text shown in the default branch column, e.g. "↑2 ↓15 origin/main".
+fn default_branch_text(default: &DefaultBranch) -> String {
+    format!("↑{} ↓{} {}", default.ahead, default.behind, default.name)
+}
 
-use crate::{cli::Args, gitinfo::RepoInfo};
+/// Returns the text describing a linked worktree, e.g. "feature-x: feature-x (Dirty, locked)".
+fn worktree_text(worktree: &WorktreeInfo) -> String {
+    let mut state = worktree.status.iter().cloned().collect::<Vec<_>>();
+    if worktree.locked {
+        state.push("locked".to_owned());
+    }
+    if worktree.prunable {
+        state.push("prunable".to_owned());
+    }
+    let branch = worktree.branch.as_deref().unwrap_or("?");
+    if state.is_empty() {
+        format!("{}: {branch}", worktree.name)
+    } else {
+        format!("{}: {branch} ({})", worktree.name, state.join(", "))
+    }
+}
+
+/// Returns the text of a commit count, e.g. "42" or "10000+" if counting stopped at the cap.
+fn commits_text(commits: usize) -> String {
+    if commits > COMMIT_COUNT_CAP {
+        format!("{COMMIT_COUNT_CAP}+")
+    } else {
+        commits.to_string()
+    }
+}
+
+/// Returns the plain-text value of a column for the given repository.
+fn cell_text(repo: &RepoInfo, column: Column) -> String {
+    match column {
+        Column::Directory => repo.name.clone(),
+        Column::Path => repo.path.display().to_string(),
+        Column::Branch => repo.branch.clone(),
+        Column::Upstream => upstream_text(repo),
+        Column::Ahead => repo.ahead.to_string(),
+        Column::Behind => repo.behind.to_string(),
+        Column::DefaultBranch => repo
+            .default_branch
+            .as_ref()
+            .map(default_branch_text)
+            .unwrap_or_default(),
+        Column::Commits => repo.commits.map(commits_text).unwrap_or_default(),
+        Column::Untracked => repo.untracked.to_string(),
+        Column::Changed => repo.changed.to_string(),
+        Column::Files => repo.files.to_string(),
+        Column::Stash => repo.stashes.to_string(),
+        Column::UnpushedBranches => repo.unpushed_branches.len().to_string(),
+        Column::Age => repo
+            .last_commit_time
+            .map(|time| util::format_age(time, util::unix_now()))
+            .unwrap_or_default(),
+        Column::Author => repo.last_commit_author.clone().unwrap_or_default(),
+        Column::LastCommit => repo.last_commit_summary.clone().unwrap_or_default(),
+        Column::Status => status_text(repo),
+        Column::Remote => repo.remote_url.clone().unwrap_or_default(),
+        Column::Remotes => repo
+            .remotes
+            .iter()
+            .map(|r| format!("{}: {}", r.name, r.url.as_deref().unwrap_or("-")))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Column::Worktrees => repo
+            .worktrees
+            .iter()
+            .map(worktree_text)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Returns the colored table cell of a column for the given repository.
+fn table_cell(repo: &RepoInfo, column: Column) -> Cell {
+    match column {
+        Column::Directory => Cell::new(&repo.name).fg(Highlight::of(repo).color()),
+        Column::Status if repo.operation.is_some() => {
+            Cell::new(status_text(repo)).fg(Color::Magenta)
+        }
+        Column::Status => match repo.status.as_str() {
+            "Clean" => Cell::new(status_text(repo)).fg(Color::Green),
+            "Dirty" => Cell::new(status_text(repo)).fg(Color::Red),
+            _ => Cell::new(&repo.status),
+        },
+        Column::Branch if repo.detached => Cell::new(&repo.branch).fg(Color::DarkYellow),
+        Column::Upstream if repo.upstream == UpstreamState::Gone => {
+            Cell::new(upstream_text(repo)).fg(Color::Red)
+        }
+        Column::Remote => Cell::new(repo.remote_url.as_deref().unwrap_or("-")),
+        Column::Remotes if repo.remotes.is_empty() => Cell::new("-"),
+        Column::DefaultBranch if repo.default_branch.is_none() => Cell::new("-"),
+        Column::Worktrees if repo.worktrees.is_empty() => Cell::new("-"),
+        Column::Path
+        | Column::Branch
+        | Column::Upstream
+        | Column::Ahead
+        | Column::Behind
+        | Column::DefaultBranch
+        | Column::Commits
+        | Column::Untracked
+        | Column::Changed
+        | Column::Files
+        | Column::Stash
+        | Column::UnpushedBranches
+        | Column::Age
+        | Column::Author
+        | Column::LastCommit
+        | Column::Remotes
+        | Column::Worktrees => Cell::new(cell_text(repo, column)),
+    }
+}
+
+/// Highlighting of a repository name, shared by all human-readable output formats.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Highlight {
+    /// The repository has unpushed commits.
+    Unpushed,
+    /// The current branch has commits but no upstream, or its upstream is gone,
+    /// so its commits may exist only locally.
+    LocalOnly,
+    /// The current branch has no commits.
+    NoCommits,
+    /// The current branch is ahead of its upstream.
+    Ahead,
+    /// The current branch is behind its upstream.
+    Behind,
+    /// Nothing noteworthy.
+    None,
+}
+
+impl Highlight {
+    /// All highlights that mark a repository, in order of precedence.
+    const MARKED: [Self; 5] = [
+        Self::Unpushed,
+        Self::LocalOnly,
+        Self::NoCommits,
+        Self::Ahead,
+        Self::Behind,
+    ];
+
+    /// Returns the highlight of the given repository.
+    const fn of(repo: &RepoInfo) -> Self {
+        if repo.has_unpushed {
+            Self::Unpushed
+        } else if repo.last_commit_time.is_some()
+            && !repo.detached
+            && !matches!(repo.upstream, UpstreamState::Tracking)
+        {
+            Self::LocalOnly
+        } else if repo.last_commit_time.is_none() {
+            Self::NoCommits
+        } else if repo.ahead > 0 {
+            Self::Ahead
+        } else if repo.behind > 0 {
+            Self::Behind
+        } else {
+            Self::None
+        }
+    }
+
+    /// Color used in the terminal table.
+    const fn color(self) -> Color {
+        match self {
+            Self::Unpushed | Self::LocalOnly => Color::Red,
+            Self::NoCommits => Color::Blue,
+            Self::Ahead => Color::Yellow,
+            Self::Behind => Color::Cyan,
+            Self::None => Color::Reset,
+        }
+    }
+
+    /// CSS class used in the HTML report.
+    const fn css_class(self) -> Option<&'static str> {
+        match self {
+            Self::Unpushed => Some("unpushed"),
+            Self::LocalOnly => Some("local-only"),
+            Self::NoCommits => Some("no-commits"),
+            Self::Ahead => Some("ahead"),
+            Self::Behind => Some("behind"),
+            Self::None => None,
+        }
+    }
+
+    /// Emoji marker used in the Markdown report.
+    const fn emoji(self) -> Option<&'static str> {
+        match self {
+            Self::Unpushed => Some("🔴"),
+            Self::LocalOnly => Some("🟥"),
+            Self::NoCommits => Some("🔵"),
+            Self::Ahead => Some("🟡"),
+            Self::Behind => Some("💠"),
+            Self::None => None,
+        }
+    }
+
+    /// Human-readable explanation, as in [`print_legend`].
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Unpushed => "Repository has unpushed commits.",
+            Self::LocalOnly => "Branch has no upstream or its upstream is gone.",
+            Self::NoCommits => "Repository has no commits in the current branch.",
+            Self::Ahead => "Repository is ahead of upstream.",
+            Self::Behind => "Repository is behind upstream.",
+            Self::None => "",
+        }
+    }
+}
+
+/// Writes the repositories as delimiter-separated values (CSV, TSV) with a header line.
+/// The columns are the same as in [`repositories_table`].
+///
+/// # Arguments
+/// * `writer` - Destination of the output.
+/// * `repos` - List of repositories to write, in display order.
+/// * `args` - CLI arguments controlling the columns.
+/// * `delimiter` - Field delimiter, e.g. `b','` or `b'\t'`.
+///
+/// # Errors
+/// Returns an error if writing to `writer` fails.
+pub fn write_delimited<W: Write>(
+    writer: W,
+    repos: &[RepoInfo],
+    args: &Args,
+    delimiter: u8,
+) -> anyhow::Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(writer);
+    let columns = args.selected_columns();
+    writer.write_record(columns.iter().map(|c| header(*c)))?;
+    for repo in repos {
+        writer.write_record(columns.iter().map(|c| cell_text(repo, *c)))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the repositories as a Markdown table followed by a legend of the markers used.
+/// The columns are the same as in [`repositories_table`].
+///
+/// # Errors
+/// Returns an error if writing to `writer` fails.
+pub fn write_markdown<W: Write>(
+    mut writer: W,
+    repos: &[RepoInfo],
+    args: &Args,
+) -> anyhow::Result<()> {
+    let columns = args.selected_columns();
+    let headers = columns.iter().map(|c| header(*c)).collect::<Vec<_>>();
+    writeln!(writer, "| {} |", headers.join(" | "))?;
+    writeln!(writer, "|{}", " --- |".repeat(headers.len()))?;
+    for repo in repos {
+        let cells = columns
+            .iter()
+            .map(|column| {
+                let value = escape_html(&cell_text(repo, *column))
+                    .replace('|', "\\|")
+                    .replace('\n', "<br>");
+                match (column, Highlight::of(repo).emoji()) {
+                    (Column::Directory, Some(marker)) => format!("{marker} {value}"),
+                    _ => value,
+                }
+            })
+            .collect::<Vec<_>>();
+        writeln!(writer, "| {} |", cells.join(" | "))?;
+    }
+    writeln!(writer)?;
+    let legend = Highlight::MARKED
+        .iter()
+        .filter_map(|h| Some(format!("{} {}", h.emoji()?, h.description())))
+        .collect::<Vec<_>>();
+    writeln!(writer, "{}", legend.join(" · "))?;
+    Ok(())
+}
+
+/// Writes the repositories as a standalone HTML page.
+/// The columns are the same as in [`repositories_table`]; the colors of the table are
+/// expressed as CSS classes.
+///
+/// # Errors
+/// Returns an error if writing to `writer` fails.
+pub fn write_html<W: Write>(mut writer: W, repos: &[RepoInfo], args: &Args) -> anyhow::Result<()> {
+    let columns = args.selected_columns();
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html lang=\"en\">")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>git-statuses</title>")?;
+    writeln!(writer, "<style>{HTML_STYLE}</style>")?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<table>")?;
+    write!(writer, "<thead><tr>")?;
+    for column in &columns {
+        write!(writer, "<th>{}</th>", header(*column))?;
+    }
+    writeln!(writer, "</tr></thead>")?;
+    writeln!(writer, "<tbody>")?;
+    for repo in repos {
+        write!(writer, "<tr>")?;
+        for column in &columns {
+            let class = css_class(repo, *column);
+            let value = escape_html(&cell_text(repo, *column)).replace('\n', "<br>");
+            match class {
+                Some(class) => write!(writer, "<td class=\"{class}\">{value}</td>")?,
+                None => write!(writer, "<td>{value}</td>")?,
+            }
+        }
+        writeln!(writer, "</tr>")?;
+    }
+    writeln!(writer, "</tbody>")?;
+    writeln!(writer, "</table>")?;
+    writeln!(writer, "<ul class=\"legend\">")?;
+    for highlight in Highlight::MARKED {
+        if let Some(class) = highlight.css_class() {
+            writeln!(
+                writer,
+                "<li class=\"{class}\">{}</li>",
+                highlight.description()
+            )?;
+        }
+    }
+    writeln!(writer, "</ul>")?;
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")?;
+    Ok(())
+}
+
+/// Stylesheet of the HTML report, mirroring the colors of the table output.
+const HTML_STYLE: &str = "table{border-collapse:collapse}\
+th,td{border:1px solid #ccc;padding:2px 8px}\
+.clean{color:green}.dirty{color:red}.in-progress{color:darkmagenta}.detached{color:darkgoldenrod}\
+.unpushed{color:red}.local-only{color:red}.gone{color:red}.no-commits{color:blue}.ahead{color:goldenrod}.behind{color:darkcyan}";
+
+/// Returns the CSS class of a column for the given repository, if it is highlighted.
+fn css_class(repo: &RepoInfo, column: Column) -> Option<&'static str> {
+    match column {
+        Column::Directory => Highlight::of(repo).css_class(),
+        Column::Branch if repo.detached => Some("detached"),
+        Column::Upstream if repo.upstream == UpstreamState::Gone => Some("gone"),
+        Column::Status if repo.operation.is_some() => Some("in-progress"),
+        Column::Status => match repo.status.as_str() {
+            "Clean" => Some("clean"),
+            "Dirty" => Some("dirty"),
+            _ => None,
+        },
+        Column::Path
+        | Column::Branch
+        | Column::Upstream
+        | Column::Ahead
+        | Column::Behind
+        | Column::DefaultBranch
+        | Column::Commits
+        | Column::Untracked
+        | Column::Changed
+        | Column::Files
+        | Column::Stash
+        | Column::UnpushedBranches
+        | Column::Age
+        | Column::Author
+        | Column::LastCommit
+        | Column::Remote
+        | Column::Remotes
+        | Column::Worktrees => None,
+    }
+}
+
+/// Escapes the characters with a special meaning in HTML.
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
 
 /// Prints the repository status information as a table or list, depending on CLI options.
 ///
 /// # Arguments
-/// * `repos` - List of repositories to display.
+/// * `repos` - List of repositories to display, in display order.
 /// * `args` - CLI arguments controlling the output format.
-pub fn repositories_table(repos: &mut [RepoInfo], args: &Args) {
+pub fn repositories_table(repos: &[RepoInfo], args: &Args) {
     if repos.is_empty() {
         log::info!("No repositories found.");
         return;
@@ -17,56 +596,14 @@ pub fn repositories_table(repos: &mut [RepoInfo], args: &Args) {
         .load_preset(presets::UTF8_FULL)
         .set_content_arrangement(ContentArrangement::Dynamic);
 
-    let mut header = vec![
-        Cell::new("Directory").add_attribute(Attribute::Bold),
-        Cell::new("Branch").add_attribute(Attribute::Bold),
-        Cell::new("Ahead").add_attribute(Attribute::Bold),
-        Cell::new("Behind").add_attribute(Attribute::Bold),
-        Cell::new("Commits").add_attribute(Attribute::Bold),
-        Cell::new("Untracked").add_attribute(Attribute::Bold),
-        Cell::new("Status").add_attribute(Attribute::Bold),
-    ];
-    if args.remote {
-        header.push(Cell::new("Remote").add_attribute(Attribute::Bold));
-    }
-    table.set_header(header);
-    repos.sort_by_key(|r| r.name.to_ascii_lowercase());
+    let columns = args.selected_columns();
+    table.set_header(
+        columns
+            .iter()
+            .map(|c| Cell::new(header(*c)).add_attribute(Attribute::Bold)),
+    );
     for repo in repos {
-        let status_str = if repo.status == "Dirty" {
-            format!("Dirty ({} changed)", repo.changed)
-        } else {
-            repo.status.clone()
-        };
-        let status_cell = match repo.status.as_str() {
-            "Clean" => Cell::new("Clean").fg(Color::Green),
-            "Dirty" => Cell::new(&status_str).fg(Color::Red),
-            _ => Cell::new(&repo.status),
-        };
-        let name_cell = Cell::new(&repo.name).fg(if repo.has_unpushed {
-            Color::Red
-        } else if repo.commits == 0 {
-            Color::Blue
-        } else if repo.ahead > 0 {
-            Color::Yellow
-        } else if repo.behind > 0 {
-            Color::Cyan
-        } else {
-            Color::Reset
-        });
-
-        let mut row = vec![
-            name_cell,
-            Cell::new(&repo.branch),
-            Cell::new(repo.ahead),
-            Cell::new(repo.behind),
-            Cell::new(repo.commits),
-            Cell::new(repo.untracked),
-            status_cell,
-        ];
-        if args.remote {
-            row.push(Cell::new(repo.remote_url.as_deref().unwrap_or("-")));
-        }
-        table.add_row(row);
+        table.add_row(columns.iter().map(|c| table_cell(repo, *c)));
     }
     println!("{table}");
 }
@@ -76,36 +613,134 @@ pub fn print_legend() {
     println!("\nLegend:");
     println!("  Clean: No changes, no unpushed commits.");
     println!("  Dirty: Changes present, may or may not have unpushed commits.");
+    println!(
+        "  Magenta status: A merge, rebase, cherry-pick, revert, bisect or `git am` is in progress."
+    );
     println!("  Unpushed: Commits that are not pushed to the remote repository.");
-    println!("  Red: Repository has unpushed commits.");
+    println!(
+        "  Red: Repository has unpushed commits on any local branch, or its branch has no upstream or the upstream is gone."
+    );
+    println!(
+        "  Upstream: Upstream of the current branch, \"-\" if there is none or \"(gone)\" if it was deleted."
+    );
     println!("  Blue: Repository has no commits in the current branch.");
     println!("  Yellow: Repository is ahead of upstream.");
     println!("  Cyan: Repository is behind upstream.");
+    println!(
+        "  Default branch: Commits ahead (↑) of and behind (↓) the remote's default branch, e.g. origin/main."
+    );
+    println!(
+        "  Detached: HEAD is detached; the branch shows the commit and the nearest branch or tag containing it (dark yellow)."
+    );
+    println!(
+        "  Files: + staged, ~ unstaged, > renamed (staged), - deleted, ! conflicted, ? untracked files."
+    );
+    println!("  Stash: Number of stashed changes, which are easily forgotten.");
+    println!(
+        "  Worktrees: Linked worktrees of the repository; \"prunable\" ones have lost their directory."
+    );
 }
 
-/// Prints a summary of the repository scan (total, clean, dirty, unpushed).
+/// Prints a summary of the repository scan (total, clean, dirty, unpushed, stashes).
 ///
 /// # Arguments
 /// * `repos` - List of repositories to summarize.
 pub fn summary(repos: &[RepoInfo], failed: usize) {
-    let total = repos.len();
-    let clean = repos.iter().filter(|r| r.status == "Clean").count();
-    let dirty = repos.iter().filter(|r| r.status == "Dirty").count();
-    let unpushed = repos.iter().filter(|r| r.has_unpushed).count();
+    let summary = Summary::new(repos, failed);
     println!("\nSummary:");
-    println!("  Total repositories:   {total}");
-    println!("  Clean:                {clean}");
-    println!("  With changes:         {dirty}");
-    println!("  With unpushed:        {unpushed}");
-    if failed > 0 {
-        println!("  Failed to process:    {failed}");
+    println!("  Total repositories:   {}", summary.total);
+    println!("  Clean:                {}", summary.clean);
+    println!("  With changes:         {}", summary.dirty);
+    println!("  With unpushed:        {}", summary.unpushed);
+    println!("  Unpushed branches:    {}", summary.unpushed_branches);
+    println!("  With stashes:         {}", summary.stashed);
+    println!("  Detached HEAD:        {}", summary.detached);
+    if summary.failed > 0 {
+        println!("  Failed to process:    {}", summary.failed);
+    }
+}
+
+/// Writes the local branches with unpushed commits of every repository, as shown by `--details`.
+///
+/// # Arguments
+/// * `writer` - Destination of the list.
+/// * `repos` - List of repositories, in display order.
+///
+/// # Errors
+/// Returns an error if writing to `writer` fails.
+pub fn write_unpushed_details<W: Write>(mut writer: W, repos: &[RepoInfo]) -> anyhow::Result<()> {
+    let repos = repos
+        .iter()
+        .filter(|r| !r.unpushed_branches.is_empty())
+        .collect::<Vec<_>>();
+    if repos.is_empty() {
+        return Ok(());
+    }
+    writeln!(writer, "\nUnpushed branches:")?;
+    for repo in repos {
+        writeln!(writer, "  {} ({})", repo.name, repo.path.display())?;
+        for branch in &repo.unpushed_branches {
+            let commits = if branch.unpushed == 1 {
+                "commit"
+            } else {
+                "commits"
+            };
+            let count = commits_text(branch.unpushed);
+            match &branch.upstream {
+                Some(upstream) => writeln!(
+                    writer,
+                    "    {}: {count} {commits} ahead of {upstream}",
+                    branch.name
+                )?,
+                None => writeln!(
+                    writer,
+                    "    {}: {count} {commits} on no remote",
+                    branch.name
+                )?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the linked worktrees of every repository, as shown by `--details`.
+///
+/// # Arguments
+/// * `writer` - Destination of the list.
+/// * `repos` - List of repositories, in display order.
+///
+/// # Errors
+/// Returns an error if writing to `writer` fails.
+pub fn write_worktree_details<W: Write>(mut writer: W, repos: &[RepoInfo]) -> anyhow::Result<()> {
+    let repos = repos
+        .iter()
+        .filter(|r| !r.worktrees.is_empty())
+        .collect::<Vec<_>>();
+    if repos.is_empty() {
+        return Ok(());
+    }
+    writeln!(writer, "\nWorktrees:")?;
+    for repo in repos {
+        writeln!(writer, "  {} ({})", repo.name, repo.path.display())?;
+        for worktree in &repo.worktrees {
+            writeln!(
+                writer,
+                "    {} at {}",
+                worktree_text(worktree),
+                worktree.path.display()
+            )?;
+            if let Some(reason) = &worktree.lock_reason {
+                writeln!(writer, "      locked: {reason}")?;
+            }
+        }
     }
+    Ok(())
 }
 
 /// Prints a summary of failed repositories that could not be processed.
 /// # Arguments
-/// * `failed_repos` - List of repository names that failed to process.
-pub fn failed_summary(failed_repos: &[String]) {
+/// * `failed_repos` - List of repositories that failed to process.
+pub fn failed_summary(failed_repos: &[FailedRepo]) {
     if !failed_repos.is_empty() {
         log::warn!("Failed to process the following repositories:");
         for repo in failed_repos {
@@ -0,0 +1,125 @@
+use std::{
+    io::IsTerminal as _,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::{cli::Args, util::ScanResult};
+
+/// Live status line on stderr showing how far the scan has progressed.
+///
+/// The line is hidden if stderr is not a terminal, a machine-readable format is selected
+/// or `--no-progress` is given, so the counters are still tracked but never drawn.
+pub struct Progress {
+    /// The progress bar drawn on stderr.
+    bar: ProgressBar,
+    /// Whether the repositories are fetched before they are read.
+    fetch: bool,
+    /// Number of repositories fetched successfully.
+    fetched: AtomicUsize,
+    /// Number of repositories that could not be processed.
+    failed: AtomicUsize,
+}
+
+impl Progress {
+    /// Creates the progress display for the given CLI arguments.
+    pub fn new(args: &Args) -> Self {
+        let target = if Self::is_enabled(args) {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        };
+        let bar = ProgressBar::with_draw_target(None, target);
+        bar.set_style(
+            ProgressStyle::with_template("{spinner} Discovering repositories: {pos} found {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+        );
+        bar.enable_steady_tick(Duration::from_millis(100));
+        Self {
+            bar,
+            fetch: args.fetch,
+            fetched: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns true if the progress should be drawn for the given CLI arguments.
+    pub fn is_enabled(args: &Args) -> bool {
+        !args.no_progress && !args.format.is_machine_readable() && std::io::stderr().is_terminal()
+    }
+
+    /// Counts a repository found while walking the directory tree.
+    pub fn discovered(&self) {
+        self.bar.inc(1);
+    }
+
+    /// Switches from discovering to scanning the `total` discovered repositories.
+    pub fn start_scan(&self, total: usize) {
+        self.bar.set_position(0);
+        self.bar.set_length(total as u64);
+        self.update_counters();
+        self.bar.set_style(
+            ProgressStyle::with_template("{spinner} [{bar}] {pos}/{len} scanned, {prefix} {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+        );
+    }
+
+    /// Shows the repository that is currently being processed.
+    pub fn processing(&self, name: &str) {
+        self.bar.set_message(name.to_owned());
+    }
+
+    /// Counts a processed repository.
+    pub fn finished(&self, result: ScanResult<'_>) {
+        match result {
+            ScanResult::Repo(repo) => {
+                if self.fetch && !repo.fetch_timed_out {
+                    self.fetched.fetch_add(1, Ordering::Relaxed);
+                }
+                if repo.fetch_timed_out {
+                    self.failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            ScanResult::Failed(_) => {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.bar.inc(1);
+        self.update_counters();
+    }
+
+    /// Removes a discovered repository from the total, e.g. a linked worktree
+    /// that is reported as part of its main repository.
+    pub fn skipped(&self) {
+        self.bar.dec_length(1);
+    }
+
+    /// Removes the status line, so it does not mix with the output.
+    pub fn clear(&self) {
+        self.bar.finish_and_clear();
+    }
+
+    /// Number of repositories fetched successfully so far.
+    pub fn fetched(&self) -> usize {
+        self.fetched.load(Ordering::Relaxed)
+    }
+
+    /// Number of repositories that failed so far.
+    pub fn failed(&self) -> usize {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Updates the fetched and failed counters shown in the status line.
+    fn update_counters(&self) {
+        let failed = self.failed();
+        let counters = if self.fetch {
+            format!("{} fetched, {failed} failed", self.fetched())
+        } else {
+            format!("{failed} failed")
+        };
+        self.bar.set_prefix(counters);
+    }
+}
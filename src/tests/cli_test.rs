@@ -4,7 +4,7 @@ use std::str;
 
 use clap::ColorChoice;
 
-use crate::cli::Args;
+use crate::cli::{Args, Column};
 
 /// From <https://github.com/EmbarkStudios/cargo-deny/blob/f6e40d8eff6a507977b20588c842c53bc0bfd427/src/cargo-deny/main.rs#L369>
 /// Snapshot tests for the CLI commands
@@ -60,3 +60,90 @@ fn test_cli_snapshot() {
         );
     });
 }
+
+#[test]
+fn test_selected_columns_default() {
+    use clap::Parser as _;
+
+    let args = Args::parse_from(["git-statuses"]);
+    assert_eq!(args.selected_columns(), Column::DEFAULT.to_vec());
+
+    let args = Args::parse_from(["git-statuses", "--remote"]);
+    assert_eq!(
+        args.selected_columns().last(),
+        Some(&Column::Remote),
+        "--remote appends the remote column"
+    );
+}
+
+#[test]
+fn test_selected_columns_custom_order_and_hidden() {
+    use clap::Parser as _;
+
+    let args = Args::parse_from([
+        "git-statuses",
+        "--columns",
+        "status,name,changed,path",
+        "--hide-columns",
+        "path",
+    ]);
+    assert_eq!(
+        args.selected_columns(),
+        vec![Column::Status, Column::Directory, Column::Changed]
+    );
+
+    let args = Args::parse_from(["git-statuses", "--hide-columns", "upstream,untracked"]);
+    let columns = args.selected_columns();
+    assert!(!columns.contains(&Column::Upstream), "upstream is hidden");
+    assert!(!columns.contains(&Column::Untracked), "untracked is hidden");
+    assert_eq!(columns.len(), Column::DEFAULT.len() - 2);
+}
+
+#[test]
+fn test_fetch_config_from_args() {
+    use clap::Parser as _;
+
+    use crate::fetch::FetchConfig;
+
+    let args = Args::parse_from(["git-statuses"]);
+    assert!(
+        FetchConfig::from_args(&args).is_none(),
+        "no fetch by default"
+    );
+
+    let args = Args::parse_from(["git-statuses", "--fetch"]);
+    assert_eq!(
+        FetchConfig::from_args(&args).unwrap().remotes,
+        vec!["origin".to_owned()]
+    );
+
+    let args = Args::parse_from([
+        "git-statuses",
+        "--fetch",
+        "--fetch-remotes",
+        "origin,upstream",
+    ]);
+    assert_eq!(
+        FetchConfig::from_args(&args).unwrap().remotes,
+        vec!["origin".to_owned(), "upstream".to_owned()]
+    );
+
+    let args = Args::parse_from(["git-statuses", "--fetch", "--all-remotes"]);
+    assert!(FetchConfig::from_args(&args).unwrap().remotes.is_empty());
+
+    let args = Args::parse_from([
+        "git-statuses",
+        "--fetch",
+        "--jobs",
+        "2",
+        "--fetch-timeout",
+        "30",
+    ]);
+    let config = FetchConfig::from_args(&args).unwrap();
+    assert_eq!(config.timeout, Some(std::time::Duration::from_secs(30)));
+    assert_eq!(args.jobs, Some(2));
+    assert!(
+        Args::try_parse_from(["git-statuses", "--jobs", "0"]).is_err(),
+        "at least one job is required"
+    );
+}
@@ -0,0 +1,100 @@
+use std::{net::TcpListener, sync::atomic::AtomicBool, time::Duration};
+
+use git2::Repository;
+
+use crate::{
+    fetch::{self, FetchConfig, FetchTimedOut},
+    gitinfo,
+    tests::commit_file,
+};
+
+#[test]
+fn test_fetch_remote_updates_upstream() {
+    let tmp = tempfile::tempdir().unwrap();
+    let upstream = Repository::init(tmp.path().join("upstream")).unwrap();
+    commit_file(&upstream, "foo.txt", "bar");
+    let clone = Repository::clone(
+        tmp.path().join("upstream").to_str().unwrap(),
+        tmp.path().join("clone"),
+    )
+    .unwrap();
+    commit_file(&upstream, "foo.txt", "baz");
+    assert_eq!(gitinfo::get_ahead_behind(&clone), (0, 0));
+
+    fetch::fetch_remote(&clone, "origin", &AtomicBool::new(false)).unwrap();
+    assert_eq!(gitinfo::get_ahead_behind(&clone), (0, 1));
+}
+
+#[test]
+fn test_fetch_remote_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = Repository::init(tmp.path().join("repo")).unwrap();
+    let missing = fetch::fetch_remote(&repo, "origin", &AtomicBool::new(false)).unwrap_err();
+    assert!(
+        format!("{missing:#}").contains("origin"),
+        "error names the remote: {missing:#}"
+    );
+
+    let unreachable = tmp.path().join("does-not-exist");
+    repo.remote("origin", unreachable.to_str().unwrap())
+        .unwrap();
+    let result = fetch::fetch_remote(&repo, "origin", &AtomicBool::new(false));
+    assert!(result.is_err(), "fetching an unreachable remote fails");
+}
+
+#[test]
+fn test_fetch_all_remotes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let upstream = Repository::init(tmp.path().join("upstream")).unwrap();
+    commit_file(&upstream, "foo.txt", "bar");
+    let fork = Repository::init(tmp.path().join("fork")).unwrap();
+    fork.remote("upstream", tmp.path().join("upstream").to_str().unwrap())
+        .unwrap();
+
+    // The default selection only fetches origin, which does not exist
+    let default = FetchConfig {
+        remotes: vec!["origin".to_owned()],
+        ..FetchConfig::default()
+    };
+    assert!(fetch::fetch(&fork, &default).is_err(), "origin is missing");
+
+    fetch::fetch(&fork, &FetchConfig::default()).unwrap();
+    let branch = gitinfo::get_branch_name(&upstream);
+    fork.find_reference(&format!("refs/remotes/upstream/{branch}"))
+        .unwrap();
+}
+
+#[test]
+fn test_fetch_reports_every_failed_remote() {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = Repository::init(tmp.path().join("repo")).unwrap();
+    for name in ["first", "second"] {
+        repo.remote(name, tmp.path().join(name).to_str().unwrap())
+            .unwrap();
+    }
+    let error = fetch::fetch(&repo, &FetchConfig::default()).unwrap_err();
+    let message = error.to_string();
+    assert!(
+        message.contains("first") && message.contains("second"),
+        "both remotes are reported: {message}"
+    );
+}
+
+#[test]
+fn test_fetch_timeout() {
+    // A server that accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = Repository::init(tmp.path()).unwrap();
+    repo.remote("origin", &format!("git://127.0.0.1:{port}/repo.git"))
+        .unwrap();
+
+    let config = FetchConfig {
+        remotes: vec!["origin".to_owned()],
+        timeout: Some(Duration::from_millis(200)),
+    };
+    let error = fetch::fetch(&repo, &config).unwrap_err();
+    assert!(error.is::<FetchTimedOut>(), "unexpected error: {error:#}");
+    drop(listener);
+}
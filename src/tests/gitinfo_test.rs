@@ -1,8 +1,9 @@
-use std::{fs, path::Path};
+use std::{fs, path::Path, time::Instant};
 
 use git2::Repository;
 
-use crate::gitinfo;
+use crate::gitinfo::{self, UpstreamState};
+use crate::tests::commit_file;
 
 fn init_temp_repo() -> (tempfile::TempDir, git2::Repository) {
     let tmp_dir = tempfile::tempdir().unwrap();
@@ -10,6 +11,19 @@ fn init_temp_repo() -> (tempfile::TempDir, git2::Repository) {
     (tmp_dir, repo)
 }
 
+/// Creates a remote-tracking branch `origin/<branch>` at `oid` and sets it as
+/// upstream of the local `branch`.
+fn set_fake_upstream(repo: &Repository, branch: &str, oid: git2::Oid) {
+    repo.remote("origin", "https://example.com/repo.git")
+        .unwrap();
+    repo.reference(&format!("refs/remotes/origin/{branch}"), oid, true, "test")
+        .unwrap();
+    repo.find_branch(branch, git2::BranchType::Local)
+        .unwrap()
+        .set_upstream(Some(&format!("origin/{branch}")))
+        .unwrap();
+}
+
 #[test]
 fn test_get_branch_name_empty() {
     let (_tmp, repo) = init_temp_repo();
@@ -29,7 +43,7 @@ fn test_get_untracked_count() {
     let (tmp, repo) = init_temp_repo();
     let path = tmp.path().join("foo.txt");
     fs::write(&path, "bar").unwrap();
-    let count = gitinfo::get_untracked_count(&repo);
+    let count = gitinfo::get_untracked_count(&gitinfo::read_statuses(&repo).unwrap());
     assert_eq!(count, 1);
 }
 
@@ -47,7 +61,7 @@ fn test_get_changed_count() {
     repo.commit(Some("HEAD"), &sig, &sig, "msg", &tree, &[])
         .unwrap();
     fs::write(&path, "baz").unwrap();
-    let changed = gitinfo::get_changed_count(&repo);
+    let changed = gitinfo::get_changed_count(&gitinfo::read_statuses(&repo).unwrap());
     assert_eq!(changed, 1);
 }
 
@@ -64,10 +78,10 @@ fn test_get_repo_status_clean_dirty() {
     let tree = repo.find_tree(oid).unwrap();
     repo.commit(Some("HEAD"), &sig, &sig, "msg", &tree, &[])
         .unwrap();
-    let status_clean = gitinfo::get_repo_status(&repo);
+    let status_clean = gitinfo::get_repo_status(&gitinfo::read_statuses(&repo).unwrap());
     assert_eq!(status_clean, "Clean");
     fs::write(&path, "baz").unwrap();
-    let status_dirty = gitinfo::get_repo_status(&repo);
+    let status_dirty = gitinfo::get_repo_status(&gitinfo::read_statuses(&repo).unwrap());
     assert_eq!(status_dirty, "Dirty");
 }
 
@@ -112,6 +126,72 @@ fn test_get_branch_name_detached_head() {
     assert!(!branch.is_empty());
 }
 
+#[test]
+fn test_describe_detached_head() {
+    let (_tmp, repo) = init_temp_repo();
+    let first = crate::tests::commit_file(&repo, "foo.txt", "foo");
+    crate::tests::commit_file(&repo, "foo.txt", "bar");
+    crate::tests::commit_file(&repo, "foo.txt", "baz");
+    let branch = gitinfo::get_branch_name(&repo);
+    let short_id = repo
+        .find_object(first, None)
+        .unwrap()
+        .short_id()
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    repo.set_head_detached(first).unwrap();
+    assert_eq!(
+        gitinfo::get_branch_name(&repo),
+        format!("detached at {short_id} ({branch}~2)")
+    );
+
+    // Tags are only used if no branch contains the commit
+    let commit = repo.find_commit(first).unwrap();
+    repo.tag_lightweight("v1.0", commit.as_object(), false)
+        .unwrap();
+    assert_eq!(
+        gitinfo::describe_detached_head(&repo),
+        Some(format!("{short_id} ({branch}~2)"))
+    );
+    let sig = repo.signature().unwrap();
+    let orphan = repo
+        .commit(None, &sig, &sig, "off", &commit.tree().unwrap(), &[&commit])
+        .unwrap();
+    let orphan = repo.find_commit(orphan).unwrap();
+    repo.tag_lightweight("v1.1", orphan.as_object(), false)
+        .unwrap();
+    repo.set_head_detached(orphan.id()).unwrap();
+    let orphan_id = orphan.as_object().short_id().unwrap();
+    assert_eq!(
+        gitinfo::describe_detached_head(&repo),
+        Some(format!("{} (v1.1)", orphan_id.as_str().unwrap()))
+    );
+}
+
+#[test]
+fn test_describe_detached_head_after_merge() {
+    let (_tmp, repo) = init_temp_repo();
+    let base = commit_file(&repo, "foo.txt", "base");
+    let base = repo.find_commit(base).unwrap();
+    let sig = repo.signature().unwrap();
+    let tree = base.tree().unwrap();
+    let side = repo
+        .commit(None, &sig, &sig, "side", &tree, &[&base])
+        .unwrap();
+    let side = repo.find_commit(side).unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "merge", &tree, &[&base, &side])
+        .unwrap();
+    let side_id = side.as_object().short_id().unwrap();
+    let side_id = side_id.as_str().unwrap().to_owned();
+
+    // The side commit is one commit behind the branch, but `<branch>~1` names the base
+    repo.set_head_detached(side.id()).unwrap();
+    assert_eq!(gitinfo::describe_detached_head(&repo), Some(side_id));
+}
+
 #[test]
 fn test_get_total_commits_multiple() {
     let (tmp, repo) = init_temp_repo();
@@ -141,12 +221,12 @@ fn test_get_total_commits_multiple() {
 
 #[test]
 fn test_repo_info_new_with_and_without_remote() {
-    let (tmp, repo) = init_temp_repo();
+    let (tmp, mut repo) = init_temp_repo();
     // Without remote
-    let info = crate::gitinfo::RepoInfo::new(&repo, false, false, tmp.path());
+    let info = crate::gitinfo::RepoInfo::new(&mut repo, false, false, false, tmp.path());
     info.unwrap();
     // With remote (origin does not exist)
-    let info_remote = crate::gitinfo::RepoInfo::new(&repo, true, false, tmp.path());
+    let info_remote = crate::gitinfo::RepoInfo::new(&mut repo, true, false, false, tmp.path());
     info_remote.unwrap();
 }
 
@@ -179,3 +259,481 @@ fn test_get_total_commits_error_cases() {
     let commits = crate::gitinfo::get_total_commits(&repo).unwrap();
     assert_eq!(commits, 0);
 }
+
+#[test]
+fn test_get_last_commit_time() {
+    let (tmp, repo) = init_temp_repo();
+    assert_eq!(gitinfo::get_last_commit_time(&repo), None);
+    std::fs::write(tmp.path().join("foo.txt"), "bar").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("foo.txt")).unwrap();
+    let oid = index.write_tree().unwrap();
+    let tree = repo.find_tree(oid).unwrap();
+    let sig = git2::Signature::new(
+        "Test",
+        "test@example.com",
+        &git2::Time::new(1_700_000_000, 0),
+    )
+    .unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "msg", &tree, &[])
+        .unwrap();
+    assert_eq!(gitinfo::get_last_commit_time(&repo), Some(1_700_000_000));
+}
+
+#[test]
+fn test_last_commit_author_and_summary() {
+    let (tmp, repo) = init_temp_repo();
+    assert_eq!(gitinfo::get_last_commit_author(&repo), None);
+    assert_eq!(gitinfo::get_last_commit_summary(&repo), None);
+    fs::write(tmp.path().join("foo.txt"), "bar").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("foo.txt")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("Jane Doe", "jane@example.com").unwrap();
+    repo.commit(
+        Some("HEAD"),
+        &sig,
+        &sig,
+        "Add foo\n\nLonger description.",
+        &tree,
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        gitinfo::get_last_commit_author(&repo).as_deref(),
+        Some("Jane Doe")
+    );
+    assert_eq!(
+        gitinfo::get_last_commit_summary(&repo).as_deref(),
+        Some("Add foo")
+    );
+}
+
+#[test]
+fn test_get_upstream() {
+    let (_tmp, repo) = init_temp_repo();
+    assert_eq!(gitinfo::get_upstream(&repo), (UpstreamState::None, None));
+    let oid = commit_file(&repo, "foo.txt", "bar");
+    assert_eq!(gitinfo::get_upstream(&repo), (UpstreamState::None, None));
+    let branch = gitinfo::get_branch_name(&repo);
+    set_fake_upstream(&repo, &branch, oid);
+    let name = Some(format!("origin/{branch}"));
+    assert_eq!(
+        gitinfo::get_upstream(&repo),
+        (UpstreamState::Tracking, name.clone())
+    );
+
+    // The remote-tracking branch was deleted on the remote and pruned
+    repo.find_reference(&format!("refs/remotes/origin/{branch}"))
+        .unwrap()
+        .delete()
+        .unwrap();
+    assert_eq!(gitinfo::get_upstream(&repo), (UpstreamState::Gone, name));
+
+    repo.set_head_detached(oid).unwrap();
+    assert_eq!(gitinfo::get_upstream(&repo), (UpstreamState::None, None));
+}
+
+#[test]
+fn test_get_ahead_behind_with_upstream() {
+    let (_tmp, repo) = init_temp_repo();
+    let first = commit_file(&repo, "foo.txt", "bar");
+    let branch = gitinfo::get_branch_name(&repo);
+    set_fake_upstream(&repo, &branch, first);
+    commit_file(&repo, "foo.txt", "baz");
+    assert_eq!(gitinfo::get_ahead_behind(&repo), (1, 0));
+}
+
+#[test]
+fn test_failed_repo_display() {
+    let failed = gitinfo::FailedRepo::new(
+        Path::new("/work/broken"),
+        gitinfo::FailureStage::Fetch,
+        &anyhow::anyhow!("connection refused"),
+    );
+    assert_eq!(failed.name, "broken");
+    assert_eq!(
+        failed.to_string(),
+        "broken (/work/broken): fetch failed: connection refused"
+    );
+}
+
+#[test]
+fn test_get_remotes() {
+    let (_tmp, repo) = init_temp_repo();
+    assert!(gitinfo::get_remotes(&repo).is_empty());
+    repo.remote("origin", "https://example.com/fork.git")
+        .unwrap();
+    repo.remote("upstream", "https://example.com/main.git")
+        .unwrap();
+    let remotes = gitinfo::get_remotes(&repo);
+    let names = remotes.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["origin", "upstream"]);
+    assert_eq!(
+        remotes[1].url.as_deref(),
+        Some("https://example.com/main.git")
+    );
+}
+
+#[test]
+fn test_get_stash_count() {
+    let (tmp, mut repo) = init_temp_repo();
+    assert_eq!(gitinfo::get_stash_count(&mut repo), 0);
+    crate::tests::commit_file(&repo, "foo.txt", "foo");
+    let sig = repo.signature().unwrap();
+    for content in ["bar", "baz"] {
+        std::fs::write(tmp.path().join("foo.txt"), content).unwrap();
+        repo.stash_save(&sig, "wip", None).unwrap();
+    }
+    assert_eq!(gitinfo::get_stash_count(&mut repo), 2);
+    let info = gitinfo::RepoInfo::new(&mut repo, false, false, false, tmp.path()).unwrap();
+    assert_eq!(info.stashes, 2);
+    assert_eq!(info.status, "Clean");
+}
+
+#[test]
+fn test_operation_in_progress() {
+    let (tmp, mut repo) = init_temp_repo();
+    let oid = crate::tests::commit_file(&repo, "foo.txt", "foo");
+    let info = gitinfo::RepoInfo::new(&mut repo, false, false, false, tmp.path()).unwrap();
+    assert_eq!(info.operation, None);
+
+    // A merge stopped by conflicts leaves MERGE_HEAD behind
+    std::fs::write(tmp.path().join(".git/MERGE_HEAD"), format!("{oid}\n")).unwrap();
+    let info = gitinfo::RepoInfo::new(&mut repo, false, false, false, tmp.path()).unwrap();
+    assert_eq!(info.operation, Some(gitinfo::Operation::Merge));
+    std::fs::remove_file(tmp.path().join(".git/MERGE_HEAD")).unwrap();
+
+    std::fs::create_dir_all(tmp.path().join(".git/rebase-merge")).unwrap();
+    assert_eq!(
+        gitinfo::Operation::from_state(repo.state()),
+        Some(gitinfo::Operation::Rebase)
+    );
+    assert_eq!(
+        gitinfo::Operation::from_state(git2::RepositoryState::CherryPickSequence),
+        Some(gitinfo::Operation::CherryPick)
+    );
+}
+
+#[test]
+fn test_get_file_counts() {
+    let (tmp, repo) = init_temp_repo();
+    commit_file(&repo, "modified.txt", "foo");
+    commit_file(&repo, "deleted.txt", "foo");
+    commit_file(
+        &repo,
+        "renamed.txt",
+        "some longer content to detect the rename",
+    );
+    assert_eq!(
+        gitinfo::FileCounts::from_statuses(&gitinfo::read_statuses(&repo).unwrap()),
+        gitinfo::FileCounts::default()
+    );
+
+    fs::write(tmp.path().join("modified.txt"), "bar").unwrap();
+    fs::remove_file(tmp.path().join("deleted.txt")).unwrap();
+    fs::rename(tmp.path().join("renamed.txt"), tmp.path().join("moved.txt")).unwrap();
+    fs::write(tmp.path().join("staged.txt"), "new").unwrap();
+    fs::write(tmp.path().join("untracked.txt"), "new").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("staged.txt")).unwrap();
+    index.remove_path(Path::new("renamed.txt")).unwrap();
+    index.add_path(Path::new("moved.txt")).unwrap();
+    index.write().unwrap();
+
+    let counts = gitinfo::FileCounts::from_statuses(&gitinfo::read_statuses(&repo).unwrap());
+    assert_eq!(
+        counts,
+        gitinfo::FileCounts {
+            staged: 2,
+            unstaged: 2,
+            conflicted: 0,
+            renamed: 1,
+            deleted: 1,
+            untracked: 1,
+        }
+    );
+    assert_eq!(counts.to_string(), "+2 ~2 >1 -1 ?1");
+}
+
+#[test]
+fn test_unstaged_move_is_deleted_and_untracked() {
+    let (tmp, repo) = init_temp_repo();
+    commit_file(&repo, "old.txt", "some longer content to detect a rename");
+    fs::rename(tmp.path().join("old.txt"), tmp.path().join("new.txt")).unwrap();
+
+    let statuses = gitinfo::read_statuses(&repo).unwrap();
+    let counts = gitinfo::FileCounts::from_statuses(&statuses);
+    assert_eq!(
+        (counts.renamed, counts.deleted, counts.untracked),
+        (0, 1, 1)
+    );
+    assert_eq!(gitinfo::get_untracked_count(&statuses), 1);
+    assert_eq!(gitinfo::get_changed_count(&statuses), 1);
+}
+
+#[test]
+fn test_get_file_counts_conflicted() {
+    let (_tmp, repo) = init_temp_repo();
+    let base = commit_file(&repo, "foo.txt", "base");
+    let main = gitinfo::get_branch_name(&repo);
+    let base_commit = repo.find_commit(base).unwrap();
+    repo.branch("other", &base_commit, false).unwrap();
+    commit_file(&repo, "foo.txt", "main");
+
+    repo.set_head("refs/heads/other").unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+    let other = commit_file(&repo, "foo.txt", "other");
+    repo.set_head(&format!("refs/heads/{main}")).unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+
+    let annotated = repo.find_annotated_commit(other).unwrap();
+    repo.merge(&[&annotated], None, None).unwrap();
+    let counts = gitinfo::FileCounts::from_statuses(&gitinfo::read_statuses(&repo).unwrap());
+    assert_eq!(counts.conflicted, 1);
+    assert_eq!(counts.to_string(), "!1");
+}
+
+#[test]
+fn test_file_counts_display_empty() {
+    assert_eq!(gitinfo::FileCounts::default().to_string(), "");
+}
+
+#[test]
+fn test_counts_from_single_status_pass() {
+    let (tmp, repo) = init_temp_repo();
+    commit_file(&repo, "a.txt", "a");
+    commit_file(&repo, "b.txt", "b");
+    fs::write(tmp.path().join("a.txt"), "changed").unwrap();
+    fs::write(tmp.path().join("b.txt"), "changed").unwrap();
+    fs::write(tmp.path().join("staged.txt"), "new").unwrap();
+    fs::write(tmp.path().join("untracked.txt"), "new").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("staged.txt")).unwrap();
+    index.write().unwrap();
+
+    let statuses = gitinfo::read_statuses(&repo).unwrap();
+    assert_eq!(gitinfo::get_untracked_count(&statuses), 1);
+    assert_eq!(gitinfo::get_changed_count(&statuses), 2);
+    assert_eq!(gitinfo::get_repo_status(&statuses), "Dirty");
+    assert_eq!(
+        gitinfo::FileCounts::from_statuses(&statuses).to_string(),
+        "+1 ~2 ?1"
+    );
+}
+
+/// Returns the minimum time `f` takes over a few rounds.
+fn min_duration(f: impl Fn()) -> std::time::Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// Benchmark comparing the single status pass with the three passes, each with its own
+/// options, that were made before, over a working tree with thousands of files.
+/// It measures wall-clock time, so it is not part of the default test run:
+/// `cargo test -- --ignored single_status_pass`.
+#[test]
+#[ignore = "benchmark measuring wall-clock time"]
+fn bench_single_status_pass() {
+    const FILES: usize = 3000;
+
+    let (tmp, repo) = init_temp_repo();
+    for i in 0..FILES {
+        let dir = tmp.path().join(format!("dir{}", i % 30));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("file{i}.txt")), format!("content {i}")).unwrap();
+    }
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = repo.signature().unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "msg", &tree, &[])
+        .unwrap();
+    for i in (0..FILES).step_by(100) {
+        let file = tmp
+            .path()
+            .join(format!("dir{}", i % 30))
+            .join(format!("file{i}.txt"));
+        fs::write(file, "changed").unwrap();
+    }
+    fs::write(tmp.path().join("untracked.txt"), "new").unwrap();
+
+    let statuses_with = |untracked: bool| {
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(untracked).include_ignored(false);
+        repo.statuses(Some(&mut opts)).unwrap().len()
+    };
+    // Untracked count, changed count and status, each read with its own options
+    let separate = min_duration(|| {
+        statuses_with(true);
+        statuses_with(false);
+        statuses_with(true);
+    });
+    let single = min_duration(|| {
+        let statuses = gitinfo::read_statuses(&repo).unwrap();
+        gitinfo::get_untracked_count(&statuses);
+        gitinfo::get_changed_count(&statuses);
+        gitinfo::get_repo_status(&statuses);
+        gitinfo::FileCounts::from_statuses(&statuses);
+    });
+    assert!(
+        single * 2 < separate,
+        "single pass ({single:?}) should be much faster than separate passes ({separate:?})"
+    );
+}
+
+#[test]
+fn test_get_unpushed_branches() {
+    let (tmp, mut repo) = init_temp_repo();
+    let base = commit_file(&repo, "foo.txt", "base");
+    let main = gitinfo::get_branch_name(&repo);
+    set_fake_upstream(&repo, &main, base);
+    assert!(gitinfo::get_unpushed_branches(&repo).is_empty());
+
+    // A feature branch without upstream, two commits beyond what any remote has
+    let base_commit = repo.find_commit(base).unwrap();
+    repo.branch("feature", &base_commit, false).unwrap();
+    repo.set_head("refs/heads/feature").unwrap();
+    commit_file(&repo, "feature.txt", "one");
+    commit_file(&repo, "feature.txt", "two");
+    // A branch that was pushed under another name is not unpushed
+    repo.branch("pushed", &base_commit, false).unwrap();
+    drop(base_commit);
+    repo.set_head(&format!("refs/heads/{main}")).unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+
+    let branches = gitinfo::get_unpushed_branches(&repo);
+    assert_eq!(
+        branches,
+        [gitinfo::UnpushedBranch {
+            name: "feature".to_owned(),
+            upstream: None,
+            unpushed: 2,
+        }]
+    );
+
+    // The checked-out branch is clean and in sync, but the repository is not safe
+    let info = gitinfo::RepoInfo::new(&mut repo, false, false, false, tmp.path()).unwrap();
+    assert_eq!(info.ahead, 0);
+    assert!(info.has_unpushed);
+
+    commit_file(&repo, "foo.txt", "main");
+    let branches = gitinfo::get_unpushed_branches(&repo);
+    let names = branches.iter().map(|b| b.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["feature", main.as_str()]);
+    assert_eq!(branches[1].upstream, Some(format!("origin/{main}")));
+    assert_eq!(branches[1].unpushed, 1);
+}
+
+#[test]
+fn test_count_commits_cap_and_opt_in() {
+    let (tmp, mut repo) = init_temp_repo();
+    for content in ["one", "two", "three", "four"] {
+        commit_file(&repo, "foo.txt", content);
+    }
+    assert_eq!(gitinfo::count_commits(&repo, 10).unwrap(), 4);
+    // Counting stops one past the cap, which is shown as "<cap>+"
+    assert_eq!(gitinfo::count_commits(&repo, 2).unwrap(), 3);
+
+    let info = gitinfo::RepoInfo::new(&mut repo, false, false, false, tmp.path()).unwrap();
+    assert_eq!(info.commits, None);
+    let info = gitinfo::RepoInfo::new(&mut repo, false, true, false, tmp.path()).unwrap();
+    assert_eq!(info.commits, Some(4));
+}
+
+#[test]
+fn test_get_default_branch() {
+    let (_tmp, repo) = init_temp_repo();
+    assert_eq!(gitinfo::get_default_branch(&repo), None);
+    let base = commit_file(&repo, "foo.txt", "base");
+    let branch = gitinfo::get_branch_name(&repo);
+    repo.remote("upstream", "https://example.com/repo.git")
+        .unwrap();
+    repo.reference("refs/remotes/upstream/main", base, true, "test")
+        .unwrap();
+    // Without `refs/remotes/<remote>/HEAD` the default branch is unknown
+    assert_eq!(gitinfo::get_default_branch(&repo), None);
+    repo.reference_symbolic(
+        "refs/remotes/upstream/HEAD",
+        "refs/remotes/upstream/main",
+        true,
+        "test",
+    )
+    .unwrap();
+    commit_file(&repo, "foo.txt", "one");
+    let two = commit_file(&repo, "foo.txt", "two");
+    let default = gitinfo::get_default_branch(&repo).unwrap();
+    assert_eq!(default.name, "upstream/main");
+    assert_eq!((default.ahead, default.behind), (2, 0));
+
+    // The default branch moved on, and the local branch diverged from it
+    repo.reference("refs/remotes/upstream/main", two, true, "test")
+        .unwrap();
+    repo.reference(&format!("refs/heads/{branch}"), base, true, "test")
+        .unwrap();
+    commit_file(&repo, "bar.txt", "local");
+    let default = gitinfo::get_default_branch(&repo).unwrap();
+    assert_eq!((default.ahead, default.behind), (1, 2));
+}
+
+#[test]
+fn test_get_worktrees() {
+    let (tmp, repo) = init_temp_repo();
+    assert!(gitinfo::get_worktrees(&repo, true).is_empty());
+    commit_file(&repo, "foo.txt", "bar");
+    let worktrees = tempfile::tempdir().unwrap();
+    let dirty = repo
+        .worktree("dirty", &worktrees.path().join("dirty"), None)
+        .unwrap();
+    fs::write(worktrees.path().join("dirty/new.txt"), "new").unwrap();
+    let locked = repo
+        .worktree("locked", &worktrees.path().join("locked"), None)
+        .unwrap();
+    locked.lock(Some("on a removable drive")).unwrap();
+    repo.worktree("gone", &worktrees.path().join("gone"), None)
+        .unwrap();
+    fs::remove_dir_all(worktrees.path().join("gone")).unwrap();
+
+    let infos = gitinfo::get_worktrees(&repo, true);
+    let names = infos.iter().map(|w| w.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["dirty", "gone", "locked"]);
+    assert_eq!(infos[0].branch.as_deref(), Some("dirty"));
+    assert_eq!(infos[0].status.as_deref(), Some("Dirty"));
+    assert!(!infos[0].locked && !infos[0].prunable, "dirty is usable");
+    assert_eq!(infos[1].branch, None);
+    assert_eq!(infos[1].status, None);
+    assert!(infos[1].prunable, "the directory of gone was removed");
+    assert_eq!(infos[2].status.as_deref(), Some("Clean"));
+    assert!(infos[2].locked, "locked was locked");
+    assert_eq!(
+        infos[2].lock_reason.as_deref(),
+        Some("on a removable drive")
+    );
+    assert!(!infos[2].prunable, "a locked worktree is never prunable");
+
+    // Reading the status walks the worktree, so it is opt-in
+    let infos = gitinfo::get_worktrees(&repo, false);
+    assert_eq!(infos[0].branch.as_deref(), Some("dirty"));
+    assert_eq!(infos[0].status, None);
+
+    // A linked worktree knows its main repository, but has no worktrees itself
+    let linked = Repository::open_from_worktree(&dirty).unwrap();
+    assert!(gitinfo::get_worktrees(&linked, true).is_empty());
+    assert_eq!(
+        gitinfo::main_worktree_path(&linked).map(|p| p.canonicalize().unwrap()),
+        Some(tmp.path().canonicalize().unwrap())
+    );
+    assert_eq!(gitinfo::main_worktree_path(&repo), None);
+}
@@ -1,3 +1,63 @@
 mod cli_test;
+mod fetch_test;
 mod gitinfo_test;
+mod printer_test;
+mod progress_test;
 mod util_test;
+
+use std::{fs, path::Path};
+
+use git2::Repository;
+
+use crate::gitinfo::{FileCounts, RepoInfo, UpstreamState};
+
+/// Writes `content` to `file`, stages it and commits it on HEAD.
+pub fn commit_file(repo: &Repository, file: &str, content: &str) -> git2::Oid {
+    let workdir = repo.workdir().unwrap();
+    fs::write(workdir.join(file), content).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(file)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = repo.signature().unwrap();
+    let parents = repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_commit().ok())
+        .into_iter()
+        .collect::<Vec<_>>();
+    let parents = parents.iter().collect::<Vec<_>>();
+    repo.commit(Some("HEAD"), &sig, &sig, "msg", &tree, &parents)
+        .unwrap()
+}
+
+/// Builds a repository with fixed, machine-independent values for snapshot tests.
+pub fn repo_info(name: &str) -> RepoInfo {
+    RepoInfo {
+        name: name.to_owned(),
+        path: Path::new("/work").join(name),
+        branch: "main".to_owned(),
+        ahead: 0,
+        behind: 0,
+        commits: Some(3),
+        last_commit_time: Some(1_700_000_000),
+        last_commit_author: None,
+        last_commit_summary: None,
+        untracked: 0,
+        changed: 0,
+        files: FileCounts::default(),
+        stashes: 0,
+        status: "Clean".to_owned(),
+        operation: None,
+        has_unpushed: false,
+        unpushed_branches: Vec::new(),
+        upstream: UpstreamState::Tracking,
+        upstream_name: Some("origin/main".to_owned()),
+        default_branch: None,
+        detached: false,
+        fetch_timed_out: false,
+        remote_url: Some(format!("https://example.com/{name}.git")),
+        remotes: Vec::new(),
+        worktrees: Vec::new(),
+    }
+}
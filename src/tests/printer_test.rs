@@ -0,0 +1,308 @@
+use std::path::Path;
+
+use clap::Parser as _;
+
+use crate::cli::Args;
+use crate::gitinfo::{
+    COMMIT_COUNT_CAP, DefaultBranch, FailedRepo, FailureStage, Operation, RemoteInfo, RepoInfo,
+    UnpushedBranch, UpstreamState, WorktreeInfo,
+};
+use crate::printer::{self, JsonReport, NdjsonRecord, Summary};
+use crate::tests::repo_info;
+use crate::util::ScanResult;
+
+/// Builds a failed repository with fixed values for snapshot tests.
+fn sample_failure() -> FailedRepo {
+    FailedRepo::new(
+        Path::new("/work/broken"),
+        FailureStage::Open,
+        &anyhow::anyhow!("could not find repository"),
+    )
+}
+
+#[test]
+fn test_json_report_snapshot() {
+    let mut dirty = repo_info("dirty");
+    dirty.ahead = 2;
+    dirty.behind = 1;
+    dirty.untracked = 1;
+    dirty.changed = 3;
+    dirty.status = "Dirty".to_owned();
+    dirty.has_unpushed = true;
+    dirty.remote_url = None;
+    let repos = vec![repo_info("clean"), dirty];
+    let failed = vec![sample_failure()];
+
+    insta::assert_json_snapshot!(JsonReport::new(&repos, &failed));
+}
+
+#[test]
+fn test_json_report_empty_snapshot() {
+    insta::assert_json_snapshot!(JsonReport::new(&[], &[]));
+}
+
+#[test]
+fn test_print_json() {
+    let repos = vec![repo_info("clean")];
+    printer::print_json(&repos, &[]).unwrap();
+}
+
+#[test]
+fn test_ndjson_records_snapshot() {
+    let repos = vec![repo_info("clean")];
+    let failed = sample_failure();
+    let records = [
+        NdjsonRecord::from(ScanResult::Repo(&repos[0])),
+        NdjsonRecord::from(ScanResult::Failed(&failed)),
+        NdjsonRecord::Summary {
+            version: printer::JSON_SCHEMA_VERSION,
+            summary: Summary::new(&repos, 1),
+        },
+    ];
+    let lines = records
+        .iter()
+        .map(|record| serde_json::to_string(record).unwrap())
+        .collect::<Vec<_>>();
+    assert!(
+        lines.iter().all(|line| !line.contains('\n')),
+        "NDJSON records must be single lines"
+    );
+    insta::assert_snapshot!(lines.join("\n"));
+}
+
+/// Renders the sample repositories as delimiter-separated values.
+fn delimited(remote: bool, delimiter: u8) -> String {
+    let mut quoted = repo_info("quoted");
+    quoted.branch = "feature/\"a, b\"".to_owned();
+    quoted.remote_url = Some("https://example.com/a,b.git".to_owned());
+    let repos = vec![repo_info("clean"), quoted];
+    let mut args = Args::parse_from(["git-statuses"]);
+    args.remote = remote;
+    let mut out = Vec::new();
+    printer::write_delimited(&mut out, &repos, &args, delimiter).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_csv_snapshot() {
+    insta::assert_snapshot!(delimited(true, b','));
+}
+
+#[test]
+fn test_tsv_without_remote_snapshot() {
+    insta::assert_snapshot!(delimited(false, b'\t'));
+}
+
+/// Sample repositories covering every highlight of the human-readable formats.
+fn highlighted_repos() -> Vec<RepoInfo> {
+    let mut unpushed = repo_info("unpushed");
+    unpushed.ahead = 1;
+    unpushed.has_unpushed = true;
+    unpushed.status = "Dirty".to_owned();
+    unpushed.changed = 2;
+    let mut empty = repo_info("empty");
+    empty.commits = Some(0);
+    empty.last_commit_time = None;
+    let mut behind = repo_info("behind");
+    behind.behind = 4;
+    behind.branch = "<weird|branch>".to_owned();
+    let mut rebasing = repo_info("rebasing");
+    rebasing.operation = Some(Operation::Rebase);
+    rebasing.status = "Dirty".to_owned();
+    rebasing.changed = 1;
+    let mut gone = repo_info("gone");
+    gone.upstream = UpstreamState::Gone;
+    gone.upstream_name = Some("origin/feature".to_owned());
+    let mut detached = repo_info("detached");
+    detached.detached = true;
+    detached.upstream = UpstreamState::None;
+    detached.upstream_name = None;
+    detached.branch = "detached at 1a2b3c4 (main~1)".to_owned();
+    vec![
+        repo_info("clean"),
+        unpushed,
+        empty,
+        behind,
+        rebasing,
+        detached,
+        gone,
+    ]
+}
+
+#[test]
+fn test_markdown_snapshot() {
+    let mut out = Vec::new();
+    let args = Args::parse_from(["git-statuses"]);
+    printer::write_markdown(&mut out, &highlighted_repos(), &args).unwrap();
+    insta::assert_snapshot!(String::from_utf8(out).unwrap());
+}
+
+#[test]
+fn test_html_snapshot() {
+    let mut out = Vec::new();
+    let args = Args::parse_from(["git-statuses", "--remote"]);
+    printer::write_html(&mut out, &highlighted_repos(), &args).unwrap();
+    insta::assert_snapshot!(String::from_utf8(out).unwrap());
+}
+
+#[test]
+fn test_csv_custom_columns_snapshot() {
+    let mut dirty = repo_info("dirty");
+    dirty.status = "Dirty".to_owned();
+    dirty.changed = 4;
+    let repos = vec![repo_info("clean"), dirty];
+    let args = Args::parse_from(["git-statuses", "--columns", "status,path,changed,name"]);
+    let mut out = Vec::new();
+    printer::write_delimited(&mut out, &repos, &args, b',').unwrap();
+    insta::assert_snapshot!(String::from_utf8(out).unwrap());
+}
+
+#[test]
+fn test_csv_last_commit_snapshot() {
+    let mut repo = repo_info("repo");
+    repo.last_commit_author = Some("Jane Doe".to_owned());
+    repo.last_commit_summary = Some("Fix \"quoting\", again".to_owned());
+    let mut out = Vec::new();
+    let args = Args::parse_from(["git-statuses", "--columns", "name,author,last-commit"]);
+    printer::write_delimited(&mut out, &[repo, repo_info("empty")], &args, b',').unwrap();
+    insta::assert_snapshot!(String::from_utf8(out).unwrap());
+}
+
+#[test]
+fn test_commit_count_cap() {
+    let mut repo = repo_info("huge");
+    repo.commits = Some(COMMIT_COUNT_CAP + 1);
+    let mut out = Vec::new();
+    let args = Args::parse_from([
+        "git-statuses",
+        "--columns",
+        "name,commits",
+        "--format",
+        "tsv",
+    ]);
+    printer::write_delimited(&mut out, &[repo, repo_info("small")], &args, b'\t').unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "Directory\tCommits\nhuge\t10000+\nsmall\t3\n"
+    );
+}
+
+#[test]
+fn test_markdown_remotes_snapshot() {
+    let mut fork = repo_info("fork");
+    fork.remotes = vec![
+        RemoteInfo {
+            name: "origin".to_owned(),
+            url: Some("https://example.com/me/fork.git".to_owned()),
+        },
+        RemoteInfo {
+            name: "upstream".to_owned(),
+            url: Some("https://example.com/them/fork.git".to_owned()),
+        },
+    ];
+    let repos = vec![repo_info("clean"), fork];
+    let args = Args::parse_from(["git-statuses", "--columns", "name,remotes"]);
+    let mut out = Vec::new();
+    printer::write_markdown(&mut out, &repos, &args).unwrap();
+    insta::assert_snapshot!(String::from_utf8(out).unwrap());
+}
+
+#[test]
+fn test_default_branch_column() {
+    let mut feature = repo_info("feature");
+    feature.default_branch = Some(DefaultBranch {
+        name: "origin/main".to_owned(),
+        ahead: 2,
+        behind: 15,
+    });
+    let mut out = Vec::new();
+    let args = Args::parse_from(["git-statuses", "--columns", "name,default-branch"]);
+    printer::write_delimited(&mut out, &[feature, repo_info("unknown")], &args, b'\t').unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "Directory\tDefault branch\nfeature\t↑2 ↓15 origin/main\nunknown\t\n"
+    );
+}
+
+#[test]
+fn test_worktrees_column() {
+    let worktree = |name: &str| WorktreeInfo {
+        name: name.to_owned(),
+        path: Path::new("/work").join(name),
+        branch: Some(name.to_owned()),
+        status: Some("Clean".to_owned()),
+        locked: false,
+        lock_reason: None,
+        prunable: false,
+    };
+    let mut repo = repo_info("repo");
+    let mut locked = worktree("locked");
+    locked.status = Some("Dirty".to_owned());
+    locked.locked = true;
+    let mut gone = worktree("gone");
+    gone.branch = None;
+    gone.status = None;
+    gone.prunable = true;
+    repo.worktrees = vec![worktree("feature"), gone, locked];
+    let mut out = Vec::new();
+    let args = Args::parse_from(["git-statuses", "--columns", "name,worktrees"]);
+    printer::write_delimited(&mut out, &[repo], &args, b',').unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "Directory,Worktrees\nrepo,\"feature: feature (Clean)\ngone: ? (prunable)\nlocked: locked (Dirty, locked)\"\n"
+    );
+}
+
+#[test]
+fn test_unpushed_details() {
+    let mut repo = repo_info("repo");
+    repo.unpushed_branches = vec![
+        UnpushedBranch {
+            name: "feature".to_owned(),
+            upstream: Some("origin/feature".to_owned()),
+            unpushed: 1,
+        },
+        UnpushedBranch {
+            name: "local".to_owned(),
+            upstream: None,
+            unpushed: COMMIT_COUNT_CAP + 1,
+        },
+    ];
+    let mut out = Vec::new();
+    printer::write_unpushed_details(&mut out, &[repo_info("pushed"), repo]).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "\nUnpushed branches:\n  repo (/work/repo)\n    feature: 1 commit ahead of origin/feature\n    local: 10000+ commits on no remote\n"
+    );
+
+    let mut out = Vec::new();
+    printer::write_unpushed_details(&mut out, &[repo_info("pushed")]).unwrap();
+    assert!(
+        out.is_empty(),
+        "nothing is written without unpushed branches"
+    );
+}
+
+#[test]
+fn test_worktree_details() {
+    let mut repo = repo_info("repo");
+    repo.worktrees = vec![WorktreeInfo {
+        name: "hotfix".to_owned(),
+        path: "/work/hotfix".into(),
+        branch: Some("hotfix".to_owned()),
+        status: None,
+        locked: true,
+        lock_reason: Some("on a removable drive".to_owned()),
+        prunable: false,
+    }];
+    let mut out = Vec::new();
+    printer::write_worktree_details(&mut out, &[repo_info("single"), repo]).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "\nWorktrees:\n  repo (/work/repo)\n    hotfix: hotfix (locked) at /work/hotfix\n      locked: on a removable drive\n"
+    );
+
+    let mut out = Vec::new();
+    printer::write_worktree_details(&mut out, &[repo_info("single")]).unwrap();
+    assert!(out.is_empty(), "nothing is written without worktrees");
+}
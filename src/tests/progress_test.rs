@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use clap::Parser as _;
+
+use crate::{
+    cli::Args,
+    gitinfo::{FailedRepo, FailureStage},
+    progress::Progress,
+    util::ScanResult,
+};
+
+#[test]
+fn test_progress_disabled_for_machine_readable_output() {
+    for format in ["json", "ndjson", "csv", "tsv"] {
+        let args = Args::parse_from(["git-statuses", "--format", format]);
+        assert!(
+            !Progress::is_enabled(&args),
+            "{format} must not show progress"
+        );
+    }
+    let args = Args::parse_from(["git-statuses", "--no-progress"]);
+    assert!(!Progress::is_enabled(&args), "--no-progress hides it");
+}
+
+#[test]
+fn test_progress_counts_results() {
+    let args = Args::parse_from(["git-statuses", "--fetch", "--no-progress"]);
+    let progress = Progress::new(&args);
+    progress.discovered();
+    progress.discovered();
+    progress.discovered();
+    progress.start_scan(3);
+    progress.skipped();
+
+    let error = anyhow::anyhow!("boom");
+    let failed = FailedRepo::new(Path::new("broken"), FailureStage::Fetch, &error);
+    progress.processing("broken");
+    progress.finished(ScanResult::Failed(&failed));
+    assert_eq!(progress.fetched(), 0);
+    assert_eq!(progress.failed(), 1);
+    progress.clear();
+}
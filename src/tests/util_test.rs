@@ -1,51 +1,45 @@
 use crate::cli::Args;
-use crate::gitinfo::RepoInfo;
+use crate::cli::{SortKey, StateFilter};
+use crate::gitinfo::{
+    DefaultBranch, FailureStage, Operation, RepoInfo, UpstreamState, WorktreeInfo,
+};
 use crate::printer;
-use crate::util::find_repositories;
+use crate::tests::repo_info;
+use crate::util::{
+    EXIT_BEHIND, EXIT_DIRTY, EXIT_FAILED, EXIT_FATAL, EXIT_UNPUSHED, ScanResult, check_exit_code,
+    find_repositories, find_repositories_with, format_age, is_selected, is_stale, matches_state,
+    parse_error_exit_code, sort_repositories, unix_now,
+};
+use clap::Parser as _;
 use std::fs;
+use std::net::TcpListener;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 use tempfile::TempDir;
 
+/// Builds the default CLI arguments for scanning `dir`.
+fn args_for(dir: &Path) -> Args {
+    Args::parse_from([Path::new("git-statuses"), dir])
+}
+
 #[test]
 fn test_find_repositories_empty_dir() {
     let temp = TempDir::new().unwrap();
-    let args = Args {
-        dir: temp.path().to_path_buf(),
-        depth: 1,
-        fetch: false,
-        remote: false,
-        summary: false,
-        legend: false,
-    };
-    let (repos, _) = find_repositories(&args).unwrap();
+    let args = args_for(temp.path());
+    let (repos, _) = find_repositories(&args);
     assert!(repos.is_empty());
 }
 
 #[test]
 fn test_print_repositories_and_summary() {
-    // Dummy RepoInfo for smoke test
-    let repo = RepoInfo {
-        name: "dummy".to_owned(),
-        branch: "main".to_owned(),
-        ahead: 0,
-        behind: 0,
-        commits: 1,
-        untracked: 0,
-        status: "Clean".to_owned(),
-        changed: 0,
-        has_unpushed: false,
-        remote_url: None,
-    };
-    let args = Args {
-        dir: Path::new(".").to_path_buf(),
-        depth: 1,
-        fetch: false,
-        remote: false,
-        summary: true,
-        legend: false,
-    };
-    let mut repos = vec![repo];
-    printer::repositories_table(&mut repos, &args);
+    let repo = repo_info("dummy");
+    let mut args = args_for(Path::new("."));
+    args.summary = true;
+    let repos = vec![repo];
+    printer::repositories_table(&repos, &args);
     printer::summary(&repos, 0);
 }
 
@@ -54,40 +48,410 @@ fn test_find_repositories_with_non_git_dir() {
     let temp = TempDir::new().unwrap();
     let subdir = temp.path().join("foo");
     fs::create_dir_all(&subdir).unwrap();
-    let args = Args {
-        dir: temp.path().to_path_buf(),
-        depth: 1,
-        fetch: false,
-        remote: false,
-        summary: false,
-        legend: false,
-    };
-    let (repos, _) = find_repositories(&args).unwrap();
+    let args = args_for(temp.path());
+    let (repos, _) = find_repositories(&args);
     assert!(repos.is_empty());
 }
 
 #[test]
 fn test_print_repositories_with_remote() {
-    let repo = RepoInfo {
-        name: "dummy".to_owned(),
-        branch: "main".to_owned(),
-        ahead: 0,
-        behind: 0,
-        commits: 1,
-        untracked: 0,
-        status: "Clean".to_owned(),
-        changed: 0,
-        has_unpushed: false,
-        remote_url: Some("https://example.com".to_owned()),
-    };
-    let args = Args {
-        dir: Path::new(".").to_path_buf(),
-        depth: 1,
-        fetch: false,
-        remote: true,
-        summary: false,
-        legend: false,
+    let mut repo = repo_info("dummy");
+    repo.remote_url = Some("https://example.com".to_owned());
+    let mut args = args_for(Path::new("."));
+    args.remote = true;
+    let repos = vec![repo];
+    printer::repositories_table(&repos, &args);
+}
+
+#[test]
+fn test_find_repositories_with_reports_each_repo() {
+    let temp = TempDir::new().unwrap();
+    for name in ["alpha", "beta"] {
+        git2::Repository::init(temp.path().join(name)).unwrap();
+    }
+    let reported = AtomicUsize::new(0);
+    let (repos, failed) = find_repositories_with(&args_for(temp.path()), |result| {
+        if let ScanResult::Repo(_) = result {
+            reported.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    assert_eq!(repos.len(), 2);
+    assert!(failed.is_empty());
+    assert_eq!(reported.load(Ordering::Relaxed), repos.len());
+}
+
+fn names(repos: &[RepoInfo]) -> Vec<&str> {
+    repos.iter().map(|r| r.name.as_str()).collect()
+}
+
+#[test]
+fn test_sort_repositories() {
+    let mut repos = vec![repo_info("b"), repo_info("C"), repo_info("a")];
+    repos[0].ahead = 1;
+    repos[2].ahead = 1;
+    sort_repositories(&mut repos, SortKey::Name, false);
+    assert_eq!(names(&repos), ["a", "b", "C"]);
+
+    sort_repositories(&mut repos, SortKey::Ahead, false);
+    assert_eq!(names(&repos), ["C", "a", "b"]);
+
+    sort_repositories(&mut repos, SortKey::Ahead, true);
+    assert_eq!(names(&repos), ["b", "a", "C"]);
+}
+
+#[test]
+fn test_sort_by_behind_default() {
+    let mut repos = vec![repo_info("far"), repo_info("unknown"), repo_info("near")];
+    for (repo, behind) in repos.iter_mut().zip([40, 0, 2]) {
+        if repo.name != "unknown" {
+            repo.default_branch = Some(DefaultBranch {
+                name: "origin/main".to_owned(),
+                ahead: 0,
+                behind,
+            });
+        }
+    }
+    sort_repositories(&mut repos, SortKey::BehindDefault, true);
+    assert_eq!(names(&repos), ["far", "near", "unknown"]);
+}
+
+#[test]
+fn test_matches_state() {
+    let mut repo = repo_info("a");
+    repo.ahead = 1;
+    repo.has_unpushed = true;
+    assert!(matches_state(&repo, StateFilter::Unpushed));
+    assert!(matches_state(&repo, StateFilter::Clean));
+    assert!(!matches_state(&repo, StateFilter::Dirty));
+    assert!(!matches_state(&repo, StateFilter::NoUpstream));
+    assert!(!matches_state(&repo, StateFilter::UpstreamGone));
+    repo.upstream = UpstreamState::Gone;
+    assert!(matches_state(&repo, StateFilter::NoUpstream));
+    assert!(matches_state(&repo, StateFilter::UpstreamGone));
+    repo.upstream = UpstreamState::None;
+    repo.detached = true;
+    assert!(matches_state(&repo, StateFilter::NoUpstream));
+    assert!(matches_state(&repo, StateFilter::Detached));
+    assert!(!matches_state(&repo, StateFilter::InProgress));
+    repo.operation = Some(Operation::Merge);
+    assert!(matches_state(&repo, StateFilter::InProgress));
+
+    // A dirty linked worktree makes its main repository dirty
+    repo.worktrees = vec![WorktreeInfo {
+        name: "feature".to_owned(),
+        path: "/work/feature".into(),
+        branch: Some("feature".to_owned()),
+        status: Some("Dirty".to_owned()),
+        locked: false,
+        lock_reason: None,
+        prunable: false,
+    }];
+    assert!(matches_state(&repo, StateFilter::Dirty));
+    assert!(!matches_state(&repo, StateFilter::Clean));
+    assert_eq!(check_exit_code(&[repo], 0) & EXIT_DIRTY, EXIT_DIRTY);
+}
+
+#[test]
+fn test_is_selected() {
+    let pushed = repo_info("pushed");
+    let mut unpushed = repo_info("unpushed");
+    unpushed.ahead = 2;
+    unpushed.has_unpushed = true;
+
+    let args = Args::parse_from(["git-statuses"]);
+    assert!(is_selected(&pushed, &args));
+    assert!(is_selected(&unpushed, &args));
+
+    let args = Args::parse_from(["git-statuses", "--only", "unpushed,behind"]);
+    assert!(!is_selected(&pushed, &args));
+    assert!(is_selected(&unpushed, &args));
+
+    let args = Args::parse_from(["git-statuses", "--exclude", "unpushed"]);
+    assert!(is_selected(&pushed, &args));
+    assert!(!is_selected(&unpushed, &args));
+}
+
+#[test]
+fn test_stale_filter() {
+    const DAY: i64 = 24 * 60 * 60;
+    let now = 1_700_000_000;
+    let mut repo = repo_info("old");
+    repo.last_commit_time = None;
+    let stale = Duration::from_secs(30 * 24 * 60 * 60);
+    assert!(!is_stale(&repo, stale, now), "no commits, never stale");
+    repo.last_commit_time = Some(now - 10 * DAY);
+    assert!(!is_stale(&repo, stale, now));
+    repo.last_commit_time = Some(now - 60 * DAY);
+    assert!(is_stale(&repo, stale, now));
+
+    let args = Args::parse_from(["git-statuses", "--stale", "30days"]);
+    assert_eq!(args.stale, Some(stale));
+    repo.last_commit_time = Some(unix_now() - 60 * DAY);
+    assert!(is_selected(&repo, &args));
+    repo.last_commit_time = Some(unix_now() - DAY);
+    assert!(!is_selected(&repo, &args));
+    Args::try_parse_from(["git-statuses", "--stale", "soon"]).unwrap_err();
+}
+
+#[test]
+fn test_format_age() {
+    const DAY: i64 = 24 * 60 * 60;
+    let now = 1_700_000_000;
+    assert_eq!(format_age(now - 5, now), "just now");
+    assert_eq!(format_age(now + 60, now), "just now");
+    assert_eq!(format_age(now - 60, now), "1 minute ago");
+    assert_eq!(format_age(now - 3 * 60 * 60, now), "3 hours ago");
+    assert_eq!(format_age(now - 3 * DAY, now), "3 days ago");
+    assert_eq!(format_age(now - 20 * DAY, now), "2 weeks ago");
+    assert_eq!(format_age(now - 100 * DAY, now), "3 months ago");
+    assert_eq!(format_age(now - 800 * DAY, now), "2 years ago");
+}
+
+#[test]
+fn test_check_exit_code() {
+    let clean = repo_info("clean");
+    assert_eq!(check_exit_code(std::slice::from_ref(&clean), 0), 0);
+    assert_eq!(check_exit_code(&[], 0), 0);
+
+    let mut dirty = repo_info("dirty");
+    dirty.status = "Dirty".to_owned();
+    let mut behind = repo_info("behind");
+    behind.behind = 3;
+    let mut unpushed = repo_info("unpushed");
+    unpushed.ahead = 1;
+    unpushed.has_unpushed = true;
+
+    assert_eq!(
+        check_exit_code(&[clean.clone(), dirty.clone()], 0),
+        EXIT_DIRTY
+    );
+    assert_eq!(
+        check_exit_code(&[clean, behind.clone()], 1),
+        EXIT_BEHIND | EXIT_FAILED
+    );
+    assert_eq!(
+        check_exit_code(&[dirty, behind, unpushed], 0),
+        EXIT_DIRTY | EXIT_UNPUSHED | EXIT_BEHIND
+    );
+}
+
+#[test]
+fn test_check_exit_code_respects_filters() {
+    let temp = TempDir::new().unwrap();
+    let repo = git2::Repository::init(temp.path().join("dirty")).unwrap();
+    crate::tests::commit_file(&repo, "foo.txt", "bar");
+    fs::write(temp.path().join("dirty/foo.txt"), "changed").unwrap();
+    let dir = temp.path().to_str().unwrap();
+    let check = |extra: &[&str]| {
+        let args = Args::parse_from(["git-statuses", dir, "--check"].iter().chain(extra));
+        let (mut repos, failed) = find_repositories(&args);
+        repos.retain(|repo| is_selected(repo, &args));
+        check_exit_code(&repos, failed.len())
     };
-    let mut repos = vec![repo];
-    printer::repositories_table(&mut repos, &args);
+    // The branch has no upstream, so its commit counts as unpushed too
+    assert_eq!(check(&[]), EXIT_DIRTY | EXIT_UNPUSHED);
+    assert_eq!(check(&["--exclude", "dirty"]), 0);
+    assert_eq!(check(&["--only", "clean"]), 0);
+}
+
+#[test]
+fn test_parse_error_exit_code() {
+    let error = Args::try_parse_from(["git-statuses", "--no-such-flag"]).unwrap_err();
+    assert_eq!(parse_error_exit_code(&error), EXIT_FATAL);
+    assert_ne!(parse_error_exit_code(&error), EXIT_DIRTY);
+    let help = Args::try_parse_from(["git-statuses", "--help"]).unwrap_err();
+    assert_eq!(parse_error_exit_code(&help), 0);
+}
+
+#[test]
+fn test_find_repositories_continues_after_open_failure() {
+    let temp = TempDir::new().unwrap();
+    git2::Repository::init(temp.path().join("good")).unwrap();
+    // A `.git` directory that is not a repository cannot be opened
+    fs::create_dir_all(temp.path().join("broken").join(".git")).unwrap();
+
+    let (repos, failed) = find_repositories(&args_for(temp.path()));
+    assert_eq!(repos.len(), 1);
+    assert_eq!(repos[0].name, "good");
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].name, "broken");
+    assert_eq!(failed[0].stage, FailureStage::Open);
+    assert!(!failed[0].error.is_empty(), "failure contains the reason");
+}
+
+#[test]
+fn test_find_repositories_missing_dir() {
+    let temp = TempDir::new().unwrap();
+    let (repos, failed) = find_repositories(&args_for(&temp.path().join("missing")));
+    assert!(repos.is_empty(), "a missing directory has no repositories");
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].name, "missing");
+    assert_eq!(failed[0].stage, FailureStage::Open);
+}
+
+#[test]
+fn test_find_repositories_counts_commits_only_when_needed() {
+    let temp = TempDir::new().unwrap();
+    let repo = git2::Repository::init(temp.path().join("repo")).unwrap();
+    crate::tests::commit_file(&repo, "foo.txt", "foo");
+
+    let (repos, _) = find_repositories(&args_for(temp.path()));
+    assert_eq!(repos[0].commits, None);
+
+    let dir = temp.path().to_str().unwrap();
+    for flags in [["--columns", "name,commits"], ["--sort", "commits"]] {
+        let args = Args::parse_from(["git-statuses", dir, flags[0], flags[1]]);
+        let (repos, _) = find_repositories(&args);
+        assert_eq!(repos[0].commits, Some(1), "{flags:?}");
+    }
+}
+
+#[test]
+fn test_find_repositories_groups_worktrees() {
+    let temp = TempDir::new().unwrap();
+    let main = git2::Repository::init(temp.path().join("main")).unwrap();
+    crate::tests::commit_file(&main, "foo.txt", "bar");
+    main.worktree("feature", &temp.path().join("feature"), None)
+        .unwrap();
+    let outside = TempDir::new().unwrap();
+    main.worktree("outside", &outside.path().join("outside"), None)
+        .unwrap();
+
+    let (repos, failed) = find_repositories(&args_for(temp.path()));
+    assert!(failed.is_empty(), "no repository should fail");
+    assert_eq!(names(&repos), ["main"]);
+    let worktrees = repos[0]
+        .worktrees
+        .iter()
+        .map(|w| w.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(worktrees, ["feature", "outside"]);
+    assert_eq!(repos[0].worktrees[0].status, None);
+
+    // The status of the worktrees is only read if they are shown
+    let dir = temp.path().to_str().unwrap();
+    let args = Args::parse_from(["git-statuses", dir, "--columns", "name,worktrees"]);
+    let (repos, _) = find_repositories(&args);
+    assert_eq!(repos[0].worktrees[0].status.as_deref(), Some("Clean"));
+
+    // A dirty worktree is taken into account by the filters and --check without being shown
+    fs::write(temp.path().join("feature/foo.txt"), "changed").unwrap();
+    for (flags, kept) in [
+        (["--only", "dirty"], true),
+        (["--exclude", "dirty"], false),
+        (["--only", "clean"], false),
+    ] {
+        let args = Args::parse_from(["git-statuses", dir, flags[0], flags[1]]);
+        let (mut repos, _) = find_repositories(&args);
+        repos.retain(|repo| is_selected(repo, &args));
+        assert_eq!(repos.len(), usize::from(kept), "{flags:?}");
+    }
+    let args = Args::parse_from(["git-statuses", dir, "--check"]);
+    let (repos, _) = find_repositories(&args);
+    assert_eq!(check_exit_code(&repos, 0) & EXIT_DIRTY, EXIT_DIRTY);
+
+    // A worktree whose main repository is not scanned is reported on its own
+    let (repos, _) = find_repositories(&args_for(outside.path()));
+    assert_eq!(names(&repos), ["outside"]);
+    assert!(repos[0].worktrees.is_empty(), "a worktree has no worktrees");
+}
+
+#[test]
+fn test_find_repositories_remote_column_without_flag() {
+    let temp = TempDir::new().unwrap();
+    let repo = git2::Repository::init(temp.path().join("repo")).unwrap();
+    repo.remote("origin", "https://example.com/repo.git")
+        .unwrap();
+    let args = Args::parse_from([Path::new("git-statuses"), temp.path()]);
+    let (repos, _) = find_repositories(&args);
+    assert_eq!(repos[0].remote_url, None);
+
+    // Selecting the column is enough, `--remote` is not needed
+    let dir = temp.path().to_str().unwrap();
+    let args = Args::parse_from(["git-statuses", dir, "--columns", "name,remote"]);
+    let (repos, _) = find_repositories(&args);
+    assert_eq!(
+        repos[0].remote_url.as_deref(),
+        Some("https://example.com/repo.git")
+    );
+    let mut out = Vec::new();
+    printer::write_delimited(&mut out, &repos, &args, b',').unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "Directory,Remote\nrepo,https://example.com/repo.git\n"
+    );
+}
+
+#[test]
+fn test_find_repositories_fetch_timeout_does_not_stall_jobs() {
+    // A server that accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let temp = TempDir::new().unwrap();
+    for name in ["first", "second"] {
+        let repo = git2::Repository::init(temp.path().join(name)).unwrap();
+        repo.remote("origin", &format!("git://127.0.0.1:{port}/repo.git"))
+            .unwrap();
+    }
+    let dir = temp.path().to_str().unwrap();
+    let args = Args::parse_from([
+        "git-statuses",
+        dir,
+        "--fetch",
+        "--jobs",
+        "1",
+        "--fetch-timeout",
+        "1",
+    ]);
+
+    // The abandoned first fetch must not keep the only job busy
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || sender.send(find_repositories(&args)).ok());
+    let (mut repos, failed) = receiver.recv_timeout(Duration::from_secs(20)).unwrap();
+    sort_repositories(&mut repos, SortKey::Name, false);
+    assert_eq!(names(&repos), ["first", "second"]);
+    assert!(repos.iter().all(|repo| repo.fetch_timed_out));
+    assert_eq!(failed.len(), 2);
+    for failure in failed {
+        assert_eq!(failure.stage, FailureStage::Fetch);
+        assert_eq!(failure.error, "fetch timed out after 1s");
+    }
+    drop(listener);
+}
+
+#[test]
+fn test_find_repositories_bounds_concurrent_fetches() {
+    // A server that accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let temp = TempDir::new().unwrap();
+    for name in ["a", "b", "c", "d"] {
+        let repo = git2::Repository::init(temp.path().join(name)).unwrap();
+        repo.remote("origin", &format!("git://127.0.0.1:{port}/repo.git"))
+            .unwrap();
+    }
+    let dir = temp.path().to_str().unwrap();
+    let args = Args::parse_from([
+        "git-statuses",
+        dir,
+        "--fetch",
+        "--jobs",
+        "2",
+        "--fetch-timeout",
+        "2",
+    ]);
+    let scan = thread::spawn(move || find_repositories(&args));
+
+    // Well before the timeout, only the first two fetches have connected
+    thread::sleep(Duration::from_millis(500));
+    let mut connections = Vec::new();
+    while let Ok(connection) = listener.accept() {
+        connections.push(connection);
+    }
+    assert_eq!(connections.len(), 2);
+
+    let (repos, failed) = scan.join().unwrap();
+    assert_eq!(repos.len(), 4);
+    assert_eq!(failed.len(), 4, "every fetch timed out");
 }
@@ -1,13 +1,38 @@
-use std::sync::Arc;
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{self, AtomicUsize},
+        mpsc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Context as _;
 use log::LevelFilter;
 use parking_lot::RwLock;
-use rayon::iter::{IntoParallelRefIterator as _, ParallelIterator as _};
+use rayon::iter::{IntoParallelRefIterator as _, ParallelBridge as _, ParallelIterator as _};
 use simplelog::{ColorChoice, ConfigBuilder, TermLogger, TerminalMode};
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
 
-use crate::{cli::Args, gitinfo::RepoInfo};
+use crate::{
+    cli::{Args, Column, OutputFormat, SortKey, StateFilter},
+    fetch::{self, FetchConfig, FetchTimedOut},
+    gitinfo::{self, FailedRepo, FailureStage, RepoInfo, UpstreamState},
+    progress::Progress,
+};
+
+/// A single result of a repository scan, reported as soon as its repository has been processed.
+#[derive(Clone, Copy)]
+pub enum ScanResult<'a> {
+    /// The repository was scanned successfully.
+    Repo(&'a RepoInfo),
+    /// The repository could not be processed.
+    Failed(&'a FailedRepo),
+}
 
 /// Scans the given directory (recursively if requested) for Git repositories and collects their status information.
 ///
@@ -17,58 +42,353 @@ use crate::{cli::Args, gitinfo::RepoInfo};
 /// # Returns
 /// A tuple containing:
 /// - A vector of `RepoInfo` containing details about each found repository.
-/// - A vector of strings of failed repositories (those that could not be opened or processed).
+/// - A vector of `FailedRepo` describing the repositories that could not be opened or processed.
+///   A directory to scan that cannot be read is reported here as well.
+pub fn find_repositories(args: &Args) -> (Vec<RepoInfo>, Vec<FailedRepo>) {
+    find_repositories_with(args, |_| {})
+}
+
+/// Same as [`find_repositories`], but additionally calls `on_result` for every repository
+/// the moment it has been processed, so results can be consumed incrementally.
 ///
-/// # Errors
-/// Returns an error if the directory cannot be read or if any repository cannot be opened.
-pub fn find_repositories(args: &Args) -> anyhow::Result<(Vec<RepoInfo>, Vec<String>)> {
+/// `on_result` is called concurrently from the scanning threads, in completion order.
+pub fn find_repositories_with<F>(args: &Args, on_result: F) -> (Vec<RepoInfo>, Vec<FailedRepo>)
+where
+    F: Fn(ScanResult<'_>) + Sync,
+{
+    let repos: Arc<RwLock<Vec<RepoInfo>>> = Arc::new(RwLock::new(Vec::new()));
+    let failed_repos: Arc<RwLock<Vec<FailedRepo>>> = Arc::new(RwLock::new(Vec::new()));
+
     let min_depth = 1;
     let max_depth = if args.depth > 0 { args.depth } else { 1 };
+    let progress = Progress::new(args);
     let walker = WalkDir::new(&args.dir)
         .min_depth(min_depth)
         .max_depth(max_depth)
         .follow_links(false)
         .into_iter()
-        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            // The directory to scan itself cannot be read, e.g. because it does not exist
+            Err(e) if e.depth() == 0 => {
+                let error = anyhow::anyhow!("{e}");
+                let failed = FailedRepo::new(&args.dir, FailureStage::Open, &error);
+                on_result(ScanResult::Failed(&failed));
+                failed_repos.write().push(failed);
+                None
+            }
+            Err(e) => {
+                log::debug!("Skipping unreadable directory: {e}");
+                None
+            }
+        })
+        .filter(|entry| entry.path().is_dir() && entry.path().join(".git").exists())
+        .inspect(|_| progress.discovered())
         .collect::<Vec<_>>();
+    progress.start_scan(walker.len());
+    // Linked worktrees are matched against these to group them under their main repository
+    let discovered = walker
+        .iter()
+        .filter_map(|entry| entry.path().canonicalize().ok())
+        .collect::<HashSet<_>>();
 
-    let repos: Arc<RwLock<Vec<RepoInfo>>> = Arc::new(RwLock::new(Vec::new()));
-    let failed_repos: Arc<RwLock<Vec<String>>> = Arc::new(RwLock::new(Vec::new()));
+    let columns = args.selected_columns();
+    let show_remote = matches!(args.format, OutputFormat::Json | OutputFormat::Ndjson)
+        || columns.contains(&Column::Remote)
+        || columns.contains(&Column::Remotes);
+    let count_commits = columns.contains(&Column::Commits) || args.sort == SortKey::Commits;
+    let worktree_status = needs_worktree_status(args, &columns);
+    let fetch = FetchConfig::from_args(args);
 
-    walker.par_iter().try_for_each(|entry| {
+    let process = |entry: &DirEntry, fetched: Option<anyhow::Result<()>>| {
         let path = entry.path();
-        let repo_name = get_repo_name(path);
-        if !path.is_dir() {
-            return Ok(());
+        progress.processing(&entry.file_name().to_string_lossy());
+        let opened = git2::Repository::open(path)
+            .map_err(|e| FailedRepo::new(path, FailureStage::Open, &e.into()));
+        if let Ok(repo) = &opened
+            && is_grouped_worktree(repo, &discovered)
+        {
+            log::debug!(
+                "Reporting worktree {} with its main repository",
+                path.display()
+            );
+            progress.skipped();
+            return;
         }
-        let git_path = path.join(".git");
-        if !git_path.exists() {
-            return Ok(());
-        }
-        match git2::Repository::open(path) {
-            Ok(repo) => {
-                if let Ok(repo) = RepoInfo::new(&repo, args.remote, args.fetch, path) {
-                    repos.write().push(repo);
-                } else {
-                    // println!("Failed to process repository: {}", path.display());
-                    failed_repos.write().push(repo_name);
+        // A repository whose fetch timed out is still read, with its previous remote state
+        let mut timed_out = None;
+        let result = match fetched {
+            Some(Err(e)) if !e.is::<FetchTimedOut>() => {
+                Err(FailedRepo::new(path, FailureStage::Fetch, &e))
+            }
+            fetched => {
+                timed_out = fetched.and_then(Result::err);
+                opened.and_then(|mut repo| {
+                    RepoInfo::new(&mut repo, show_remote, count_commits, worktree_status, path)
+                })
+            }
+        };
+        match result {
+            Ok(mut repo) => {
+                repo.fetch_timed_out = timed_out.is_some();
+                progress.finished(ScanResult::Repo(&repo));
+                on_result(ScanResult::Repo(&repo));
+                if let Some(error) = timed_out {
+                    let failed = FailedRepo::new(path, FailureStage::Fetch, &error);
+                    on_result(ScanResult::Failed(&failed));
+                    failed_repos.write().push(failed);
                 }
-                Ok(())
+                repos.write().push(repo);
             }
-            Err(e) => {
-                anyhow::bail!("Could not open repository: {e}");
+            Err(failed) => {
+                progress.finished(ScanResult::Failed(&failed));
+                on_result(ScanResult::Failed(&failed));
+                failed_repos.write().push(failed);
             }
         }
-    })?;
-    Ok((repos.read().to_vec(), failed_repos.read().to_vec()))
+    };
+    if let Some(fetch) = &fetch {
+        // Fetching runs on its own threads, so slow remotes never hold up reading the
+        // repositories that have already been fetched
+        let jobs = args.jobs.unwrap_or_else(rayon::current_num_threads);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            scope.spawn(|| fetch_repositories(&walker, &discovered, fetch, jobs, sender));
+            receiver
+                .into_iter()
+                .par_bridge()
+                .for_each(|(index, fetched)| process(&walker[index], fetched));
+        });
+    } else {
+        walker.par_iter().for_each(|entry| process(entry, None));
+    }
+    progress.clear();
+    (repos.read().to_vec(), failed_repos.read().to_vec())
+}
+
+/// Returns true if the status of the linked worktrees has to be read: when it is shown, and
+/// for the state filters and `--check`, which count dirty worktrees towards their main repository.
+fn needs_worktree_status(args: &Args, columns: &[Column]) -> bool {
+    args.details
+        || columns.contains(&Column::Worktrees)
+        || args.check
+        || !args.only.is_empty()
+        || !args.exclude.is_empty()
+}
+
+/// Fetches the remotes of the discovered repositories on `jobs` threads and sends the index
+/// of every entry with its fetch result as soon as it is done.
+/// Linked worktrees grouped under their main repository are sent without being fetched,
+/// since they share its remotes.
+///
+/// # Arguments
+/// * `entries` - The discovered repositories.
+/// * `discovered` - Canonical paths of all discovered repositories.
+/// * `config` - The remotes to fetch and the fetch timeout.
+/// * `jobs` - Maximum number of repositories fetched at the same time.
+/// * `sender` - Receives the index of each entry with its fetch result, or `None` if it was not fetched.
+fn fetch_repositories(
+    entries: &[DirEntry],
+    discovered: &HashSet<PathBuf>,
+    config: &FetchConfig,
+    jobs: usize,
+    sender: mpsc::Sender<(usize, Option<anyhow::Result<()>>)>,
+) {
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(entries.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, atomic::Ordering::Relaxed);
+                    let Some(entry) = entries.get(index) else {
+                        break;
+                    };
+                    let fetched = git2::Repository::open(entry.path())
+                        .ok()
+                        .filter(|repo| !is_grouped_worktree(repo, discovered))
+                        .map(|repo| fetch::fetch(&repo, config));
+                    // The receiver lives until every entry has been processed
+                    sender.send((index, fetched)).ok();
+                }
+            });
+        }
+    });
+    // Every entry has been sent, which ends the receiving iteration
+    drop(sender);
+}
+
+/// Returns true if `repo` is a linked worktree whose main repository was discovered as well,
+/// so it is reported as part of it instead of as a separate repository.
+/// A worktree whose main repository is outside the scanned tree is not grouped.
+///
+/// # Arguments
+/// * `repo` - The opened repository.
+/// * `discovered` - Canonical paths of all discovered repositories.
+fn is_grouped_worktree(repo: &git2::Repository, discovered: &HashSet<PathBuf>) -> bool {
+    gitinfo::main_worktree_path(repo)
+        .and_then(|main| main.canonicalize().ok())
+        .is_some_and(|main| discovered.contains(&main))
 }
 
-/// Extracts the repository name from the given path.
-fn get_repo_name(path: &std::path::Path) -> String {
-    path.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_owned()
+/// Exit code of fatal errors, including invalid command line arguments.
+pub const EXIT_FATAL: u8 = 1;
+/// Exit code bit set by `--check` if any repository is dirty.
+pub const EXIT_DIRTY: u8 = 2;
+/// Exit code bit set by `--check` if any repository has unpushed commits.
+pub const EXIT_UNPUSHED: u8 = 4;
+/// Exit code bit set by `--check` if any repository is behind its upstream.
+pub const EXIT_BEHIND: u8 = 8;
+/// Exit code bit set by `--check` if any repository could not be processed.
+pub const EXIT_FAILED: u8 = 16;
+
+/// Returns the exit code for a command line that could not be parsed.
+///
+/// clap exits with 2 on usage errors, which would read as [`EXIT_DIRTY`] to a `--check`
+/// hook, so usage errors exit with [`EXIT_FATAL`] instead. `--help` and `--version` succeed.
+pub fn parse_error_exit_code(error: &clap::Error) -> u8 {
+    if error.use_stderr() { EXIT_FATAL } else { 0 }
+}
+
+/// Computes the `--check` exit code for the scan results.
+/// Only the repositories kept by `--only`, `--exclude` and `--stale` should be passed.
+///
+/// # Returns
+/// `0` if every repository is clean, pushed and up to date, otherwise the bitwise OR
+/// of the matching `EXIT_*` codes.
+pub fn check_exit_code(repos: &[RepoInfo], failed: usize) -> u8 {
+    let mut code = 0;
+    if repos.iter().any(|r| matches_state(r, StateFilter::Dirty)) {
+        code |= EXIT_DIRTY;
+    }
+    if repos.iter().any(|r| r.has_unpushed) {
+        code |= EXIT_UNPUSHED;
+    }
+    if repos.iter().any(|r| r.behind > 0) {
+        code |= EXIT_BEHIND;
+    }
+    if failed > 0 {
+        code |= EXIT_FAILED;
+    }
+    code
+}
+
+/// Returns true if the repository is in the given state.
+/// A repository with a dirty linked worktree is dirty as well; the status of the worktrees
+/// is only known if it was read during the scan.
+pub fn matches_state(repo: &RepoInfo, state: StateFilter) -> bool {
+    let dirty_worktree = || {
+        repo.worktrees
+            .iter()
+            .any(|w| w.status.as_deref() == Some("Dirty"))
+    };
+    match state {
+        StateFilter::Clean => repo.status == "Clean" && !dirty_worktree(),
+        StateFilter::Dirty => repo.status == "Dirty" || dirty_worktree(),
+        StateFilter::Unpushed => repo.has_unpushed,
+        StateFilter::Behind => repo.behind > 0,
+        StateFilter::NoUpstream => repo.upstream != UpstreamState::Tracking,
+        StateFilter::UpstreamGone => repo.upstream == UpstreamState::Gone,
+        StateFilter::Detached => repo.detached,
+        StateFilter::InProgress => repo.operation.is_some(),
+    }
+}
+
+/// Returns true if the repository passes the `--only`, `--exclude` and `--stale` filters:
+/// it must be in any of the `--only` states (if given), in none of the `--exclude` states
+/// and its last commit must be older than `--stale` (if given).
+pub fn is_selected(repo: &RepoInfo, args: &Args) -> bool {
+    (args.only.is_empty() || args.only.iter().any(|s| matches_state(repo, *s)))
+        && !args.exclude.iter().any(|s| matches_state(repo, *s))
+        && args
+            .stale
+            .is_none_or(|stale| is_stale(repo, stale, unix_now()))
+}
+
+/// Returns true if the last commit of the repository is older than `stale` at `now`
+/// (seconds since the Unix epoch). Repositories without commits are never stale.
+pub fn is_stale(repo: &RepoInfo, stale: Duration, now: i64) -> bool {
+    let stale = i64::try_from(stale.as_secs()).unwrap_or(i64::MAX);
+    repo.last_commit_time
+        .is_some_and(|time| now.saturating_sub(time) > stale)
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| i64::try_from(now.as_secs()).unwrap_or(i64::MAX))
+}
+
+/// Formats the time between `time` and `now` (seconds since the Unix epoch)
+/// as a relative time, e.g. "3 days ago".
+pub fn format_age(time: i64, now: i64) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const WEEK: i64 = 7 * DAY;
+    const MONTH: i64 = 30 * DAY;
+    const YEAR: i64 = 365 * DAY;
+
+    let age = now.saturating_sub(time);
+    let (count, unit) = if age < MINUTE {
+        return "just now".to_owned();
+    } else if age < HOUR {
+        (age / MINUTE, "minute")
+    } else if age < DAY {
+        (age / HOUR, "hour")
+    } else if age < 2 * WEEK {
+        (age / DAY, "day")
+    } else if age < 2 * MONTH {
+        (age / WEEK, "week")
+    } else if age < YEAR {
+        (age / MONTH, "month")
+    } else {
+        (age / YEAR, "year")
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural} ago")
+}
+
+/// Returns the number of commits behind the default branch, or `None` if it is unknown,
+/// which sorts before any count.
+fn behind_default(repo: &RepoInfo) -> Option<usize> {
+    repo.default_branch.as_ref().map(|default| default.behind)
+}
+
+/// Sorts the repositories by the given key, breaking ties by the case-insensitive name.
+/// `reverse` reverses the whole order, including the tie-breaker.
+///
+/// # Arguments
+/// * `repos` - Repositories to sort in place.
+/// * `key` - Primary sort key.
+/// * `reverse` - Whether to sort in descending order.
+pub fn sort_repositories(repos: &mut [RepoInfo], key: SortKey, reverse: bool) {
+    repos.sort_by(|a, b| {
+        let ordering = match key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Path => a.path.cmp(&b.path),
+            SortKey::Branch => a.branch.cmp(&b.branch),
+            SortKey::Ahead => a.ahead.cmp(&b.ahead),
+            SortKey::Behind => a.behind.cmp(&b.behind),
+            SortKey::BehindDefault => behind_default(a).cmp(&behind_default(b)),
+            SortKey::Changed => a.changed.cmp(&b.changed),
+            SortKey::Commits => a.commits.cmp(&b.commits),
+            SortKey::LastCommit => a.last_commit_time.cmp(&b.last_commit_time),
+            SortKey::Status => a.status.cmp(&b.status),
+        }
+        .then_with(|| {
+            a.name
+                .to_ascii_lowercase()
+                .cmp(&b.name.to_ascii_lowercase())
+        });
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
 }
 
 /// Initializes the logger for the application.